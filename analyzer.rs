@@ -6,31 +6,49 @@
 
 use SymbolTable;
 use builtin::Ty;
+use pre_record;
+use moves::{MoveState,MoveAnalysis};
+use cfg::{Cfg,Action,RegionKind};
+use summary::{self,SummaryBuilder,FnSummary};
 use VarInfo;
+use CallInfo;
+use CloneInfo;
+use ConversionInfo;
+use BorrowedMove;
+use MutBinding;
+use MutBorrow;
+use ReturnInfo;
+use ResultUse;
+use FieldInit;
+use builtin::Mode;
+use cfg::Place;
 use std::collections::{HashMap};
-use std::io::{BufReader,BufRead};
-use std::fs::File;
+
+// Values of more bytes than this are costly to move or copy often
+const LARGE_SIZE: usize = 128;
 
 impl <'a> SymbolTable<'a>{
 
     // Entry point of this file, start to analyze the symbol table
     // print out the generated advice
     pub fn start_analyze(&self,
-                                      file_name:String
+                                      source:&str
                                       ) 
     {
         println!("========================================================");
         println!("Adivice one (drop): \n");
         let mut print_list = HashMap::new();
-        for (var,info) in &self.var_table{
+        for (var,info) in self.sorted_vars(){
             if !self.check_move(var){
                 match info.var_type{
                    Ty::NonPrimitive=>
                     {  
-                        self.choice_one_drop(&var,&info,&mut print_list);
+                        self.choice_one_drop(var,info,&mut print_list);
                     },
                     _=>(),
                 }
+            }else{
+                self.choice_one_drop_fields(var,info,&mut print_list);
             }
         }
 
@@ -41,11 +59,8 @@ impl <'a> SymbolTable<'a>{
         if print_list.is_empty(){
             println!("No adivice!");
         }else{
-            let file = File::open(file_name).unwrap();
-            let file_reader = BufReader::new(&file);
             let mut line_num:usize = 1;
-            for line in file_reader.lines(){
-                let l = line.unwrap();
+            for l in source.lines(){
                 if let Some(print_lines) = print_list.get(&line_num){
                     for print_line in print_lines{
                         println!("{}", print_line);
@@ -62,25 +77,61 @@ impl <'a> SymbolTable<'a>{
         println!("Adivice two (fucntion): \n");
         let mut print_list_func:HashMap<String,Vec<bool>> = HashMap::new();
         self.choice_two_function(&mut print_list_func);
+        let mut builders = vec![];
+        self.collect_summaries(&mut builders);
+        let summaries = summary::summarize(&builders);
         let mut printed = false;
+        // Functions are reported in the order of their names
+        let mut print_list_func:Vec<(String,Vec<bool>)> = print_list_func.into_iter().collect();
+        print_list_func.sort_by(|a,b| a.0.cmp(&b.0));
         for (print_fun, prints) in print_list_func {
             if (!String::eq(&print_fun,"main")) & (!String::eq(&print_fun,"new")) {
-                let mut can_print = true;
-                for(struct_name, _ ) in self.structure_list{
-                    let new_method = struct_name.to_string() +"new";
-                    if String::eq(&new_method,&print_fun){
-                        can_print = false;
-                        break;
-                    }
-                }  
+                // Constructors, for example Point::new, take the values they
+                // are built from
+                let can_print = !print_fun.ends_with("::new");
                 if can_print{
                     printed = true;
                     println!("{}:",print_fun);
                     let mut printed = false;
                     let mut index = 0;
                     while index< prints.len(){
-                        if prints[index] == true{
-                            println!("\targument {} can take ownership ", index+1 );
+                        let param = param_summary(&summaries,&print_fun,index);
+                        if let Some(param) = param{
+                            println!("\targument {} (`{}`): {}", index+1, param.name, param.describe());
+                        }
+                        // A parameter bounded by Copy is copied whether or not
+                        // the callers still need their arguments
+                        let note = match self.generic_note(&print_fun,index){
+                            Some(note) => note,
+                            None => {
+                                println!("\targument {} is copied since its type is bounded by Copy", index+1);
+                                printed = true;
+                                index+=1;
+                                continue
+                            },
+                        };
+                        // The reverse advice: a value taken by value but only
+                        // read can be borrowed, which saves the callers a clone
+                        let borrow = self.choice_borrow_param(&print_fun,index,param);
+                        if let Some(ref advice) = borrow{
+                            println!("{}",advice);
+                            printed = true;
+                        }
+                        if prints[index] && borrow.is_none(){
+                            // Ownership is only worth taking when the function
+                            // moves, returns or stores the value
+                            if param.map_or(false, |param| param.unused()){
+                                println!("\targument {} is never used by the function", index+1);
+                            }else if param.map_or(false, |param| !param.consumed()){
+                                println!("\targument {} is only read or changed by the function, it does not need to take ownership", index+1);
+                            }else{
+                                println!("\targument {} can take ownership {}", index+1, note);
+                                if let Some(&(ref ty, size)) = self.fun_records.get(&print_fun).and_then(|info| info.params.get(index)).and_then(|param| param.value_size.as_ref()){
+                                    if size > LARGE_SIZE{
+                                        println!("\targument {} is about {} bytes, each move copies all of them, `Box<{}>` moves a pointer instead", index+1, size, ty);
+                                    }
+                                }
+                            }
                             printed = true;
                         }
                         index+=1;
@@ -95,24 +146,267 @@ impl <'a> SymbolTable<'a>{
             println!("\n No advice for funciton");
         }
          println!("========================================================");
+        println!("Adivice three (moves): \n");
+        let mut analyses = vec![];
+        self.collect_move_analyses(&mut analyses);
+        let mut printed = false;
+        for analysis in analyses{
+            printed |= print_move_analysis(analysis);
+        }
+        let mut advice = vec![];
+        self.choice_partial_move(&mut advice);
+        let mut moves = vec![];
+        self.collect_borrowed_moves(&mut moves);
+        moves.sort_by(|a,b| a.line.cmp(&b.line));
+        for borrowed_move in moves{
+            advice.push(format!("line {}: `{}` clones a field behind a mutable reference\n\tAdivice: if the old value is not needed afterwards, take it with `{}` instead of cloning it",
+                                    borrowed_move.line, borrowed_move.cloned, borrowed_move.rewritten));
+        }
+        for line in &advice{
+            println!("{}",line);
+        }
+        printed |= !advice.is_empty();
+        if !printed{
+            println!("No advice for moves");
+        }
+         println!("========================================================");
+        println!("Adivice four (scopes): \n");
+        let mut cfgs = vec![];
+        self.collect_cfgs(&mut cfgs);
+        let mut advice = vec![];
+        self.choice_narrow_scope(&cfgs,&mut advice);
+        if advice.is_empty(){
+            println!("No advice for scopes");
+        }else{
+            advice.sort();
+            for (_, line) in advice{
+                println!("{}",line);
+            }
+        }
+         println!("========================================================");
+        println!("Adivice five (borrowed types): \n");
+        let mut advice = vec![];
+        self.choice_general_borrow(&summaries,&mut advice);
+        if advice.is_empty(){
+            println!("No advice for borrowed types");
+        }
+        for line in &advice{
+            println!("{}",line);
+        }
+         println!("========================================================");
+        println!("Adivice six (clones): \n");
+        let mut clones = vec![];
+        self.collect_clones(&mut clones);
+        clones.sort_by(|a,b| a.line.cmp(&b.line));
+        let mut printed = false;
+        for clone in clones{
+            if let Some(advice) = self.choice_redundant_clone(clone){
+                println!("{}",advice);
+                printed = true;
+            }
+        }
+        if !printed{
+            println!("No advice for clones");
+        }
+         println!("========================================================");
+        println!("Adivice seven (conversions): \n");
+        let mut conversions = vec![];
+        self.collect_conversions(&mut conversions);
+        conversions.sort_by(|a,b| a.line.cmp(&b.line));
+        let mut printed = false;
+        for conversion in conversions{
+            if let Some(advice) = self.choice_owned_conversion(conversion,&summaries){
+                println!("{}",advice);
+                printed = true;
+            }
+        }
+        if !printed{
+            println!("No advice for conversions");
+        }
+         println!("========================================================");
+        println!("Adivice eight (mutability): \n");
+        let mut advice = vec![];
+        self.choice_unneeded_mut(&summaries,&mut advice);
+        if advice.is_empty(){
+            println!("No advice for mutability");
+        }else{
+            advice.sort();
+            for (_, line) in advice{
+                println!("{}",line);
+            }
+        }
+         println!("========================================================");
+        println!("Adivice nine (getters): \n");
+        let mut advice = vec![];
+        self.choice_return_reference(&cfgs,&mut advice);
+        if advice.is_empty(){
+            println!("No advice for getters");
+        }
+        for line in &advice{
+            println!("{}",line);
+        }
+         println!("========================================================");
+        println!("Adivice ten (structures): \n");
+        let mut advice = vec![];
+        self.choice_field_ownership(&mut advice);
+        if advice.is_empty(){
+            println!("No advice for structures");
+        }
+        for line in &advice{
+            println!("{}",line);
+        }
+         println!("========================================================");
+        println!("Adivice eleven (sizes): \n");
+        let mut advice = vec![];
+        self.choice_large_values(&cfgs,&summaries,&mut advice);
+        if advice.is_empty(){
+            println!("No advice for sizes");
+        }
+        for line in &advice{
+            println!("{}",line);
+        }
+         println!("========================================================");
         
      }
 
+     // Collect the move analyses of the functions defined in this scope
+     // and in its enclosing scopes
+     fn collect_move_analyses<'b>(&'b self, analyses:&mut Vec<&'b MoveAnalysis>){
+         for analysis in &self.move_analyses{
+             analyses.push(analysis);
+         }
+         for scope in &self.enclose_scope{
+             scope.collect_move_analyses(analyses);
+         }
+     }
+
+
+     // Collect the effects on the parameters of the functions defined in
+     // this scope and in its enclosing scopes
+     fn collect_summaries<'b>(&'b self, builders:&mut Vec<&'b SummaryBuilder>){
+         for builder in &self.summaries{
+             builders.push(builder);
+         }
+         for scope in &self.enclose_scope{
+             scope.collect_summaries(builders);
+         }
+     }
+
+     // Collect the calls of a function made in this scope and in its
+     // enclosing scopes
+     fn collect_calls<'b>(&'b self, func_name:&str, calls:&mut Vec<&'b CallInfo>){
+         if let Some(infos) = self.call_records.get(func_name){
+             for info in infos{
+                 calls.push(info);
+             }
+         }
+         for scope in &self.enclose_scope{
+             scope.collect_calls(func_name,calls);
+         }
+     }
+
+     // Collect the values cloned in this scope and in its enclosing scopes
+     fn collect_clones<'b>(&'b self, clones:&mut Vec<&'b CloneInfo>){
+         for clone in &self.clone_records{
+             clones.push(clone);
+         }
+         for scope in &self.enclose_scope{
+             scope.collect_clones(clones);
+         }
+     }
+
+     // Collect the owned conversions in this scope and in its enclosing
+     // scopes
+     fn collect_conversions<'b>(&'b self, conversions:&mut Vec<&'b ConversionInfo>){
+         for conversion in &self.conversion_records{
+             conversions.push(conversion);
+         }
+         for scope in &self.enclose_scope{
+             scope.collect_conversions(conversions);
+         }
+     }
+
+     // Collect the clones of fields behind mutable references in this scope and in
+     // its enclosing scopes
+     fn collect_borrowed_moves<'b>(&'b self, moves:&mut Vec<&'b BorrowedMove>){
+         for borrowed_move in &self.borrowed_moves{
+             moves.push(borrowed_move);
+         }
+         for scope in &self.enclose_scope{
+             scope.collect_borrowed_moves(moves);
+         }
+     }
+
+     // Collect the changed places, the lent places, the `let mut` bindings
+     // and the `&mut` borrows of this scope and of its enclosing scopes
+     fn collect_mutability<'b>(&'b self,
+                                             mutated:&mut Vec<&'b Place>,
+                                             lent:&mut Vec<&'b (Place,String,usize)>,
+                                             bindings:&mut Vec<&'b MutBinding>,
+                                             borrows:&mut Vec<&'b MutBorrow>)
+     {
+         mutated.extend(self.mutated_places.iter());
+         lent.extend(self.lent_places.iter());
+         bindings.extend(self.mut_bindings.iter());
+         borrows.extend(self.mut_borrows.iter());
+         for scope in &self.enclose_scope{
+             scope.collect_mutability(mutated,lent,bindings,borrows);
+         }
+     }
+
+     // Collect the returned values and the kept call results of this scope
+     // and of its enclosing scopes
+     fn collect_returns<'b>(&'b self,
+                                         returns:&mut Vec<&'b ReturnInfo>,
+                                         uses:&mut Vec<&'b ResultUse>)
+     {
+         returns.extend(self.returns.iter());
+         uses.extend(self.result_uses.iter());
+         for scope in &self.enclose_scope{
+             scope.collect_returns(returns,uses);
+         }
+     }
+
+     // Collect the initialised fields of the structure literals and the
+     // changed fields of this scope and of its enclosing scopes
+     fn collect_fields<'b>(&'b self,
+                                       inits:&mut Vec<&'b FieldInit>,
+                                       writes:&mut Vec<&'b (String,String)>)
+     {
+         inits.extend(self.field_inits.iter());
+         writes.extend(self.field_writes.iter());
+         for scope in &self.enclose_scope{
+             scope.collect_fields(inits,writes);
+         }
+     }
+
+     // Collect the control flow graphs of the functions defined in this
+     // scope and in its enclosing scopes
+     fn collect_cfgs<'b>(&'b self, cfgs:&mut Vec<&'b Cfg>){
+         for cfg in &self.cfgs{
+             cfgs.push(cfg);
+         }
+         for scope in &self.enclose_scope{
+             scope.collect_cfgs(cfgs);
+         }
+     }
 
      // Help analyze drop for inner scope
      fn drop_analyze_innerscope(&self, 
                                                     mut print_list:&mut HashMap<usize,Vec<String>>)
      {
-          for (var,info) in &self.var_table{
+          for (var,info) in self.sorted_vars(){
 
               if !self.check_move(var){
                   match info.var_type{
                       Ty::NonPrimitive=>
                       {  
-                          self.choice_one_drop(&var,&info,&mut print_list);
+                          self.choice_one_drop(var,info,&mut print_list);
                       },
                       _=>(),
                   }
+              }else{
+                  self.choice_one_drop_fields(var,info,&mut print_list);
               }
           }
           for scope in & self.enclose_scope{
@@ -139,9 +433,421 @@ impl <'a> SymbolTable<'a>{
         }
      }
 
+     // Advise a parameter borrowed as &String, &Vec<T> or &Box<T> to be
+     // borrowed as &str, &[T] or &T when the function only uses what the
+     // more general type offers. The calls do not change, since the
+     // argument is coerced to the more general type
+     fn choice_general_borrow(&self,
+                                             summaries:&HashMap<String,FnSummary>,
+                                             advice:&mut Vec<String>)
+     {
+         let mut names:Vec<&String> = self.fun_records.keys().collect();
+         names.sort();
+         for name in names{
+             let info = &self.fun_records[name];
+             let mut index = 0;
+             while index < info.params.len(){
+                 let param = &info.params[index];
+                 index+=1;
+                 let &(ref declared, ref general) = match param.general{
+                     Some(ref general) => general,
+                     None => continue,
+                 };
+                 // A reference that is returned or stored keeps its type in
+                 // the signature of the function or in a field
+                 if let Some(summary) = param_summary(summaries,name,index-1){
+                     if summary.concrete | summary.returned | summary.stored | summary.moved{
+                         continue
+                     }
+                 }
+                 advice.push(format!("{}:\n\targument {} (`{}`) can be borrowed as `{}` instead of `{}`",
+                                        name, index, param.name, general, declared));
+                 let mut calls = vec![];
+                 self.collect_calls(name,&mut calls);
+                 calls.sort_by(|a,b| a.call_location.cmp(&b.call_location));
+                 let sites:Vec<String> = calls.iter().map(|call| match call.places.get(index-1){
+                     Some(&Some(ref place)) => format!("line {} (`{}`)", call.call_location, place.var.join(".")),
+                     _ => format!("line {}", call.call_location),
+                 }).collect();
+                 if sites.is_empty(){
+                     advice.push("\tthe function is not called".to_string());
+                 }else{
+                     advice.push(format!("\tthe calls on {} do not need to change", sites.join(", ")));
+                 }
+             }
+         }
+     }
+
+     // Advise moving a variable instead of cloning it when the clone is the
+     // last use of the variable on every path and nothing borrows the
+     // variable afterwards
+     fn choice_redundant_clone(&self, clone:&CloneInfo) -> Option<String>{
+         if clone.live_after{
+             return None
+         }
+         let var = clone.var.join(".");
+         if let Some(info) = self.find_var_info(&clone.var[0]){
+             let mut visited = vec![clone.var[0].clone()];
+             for refer in &info.ref_by{
+                 if self.borrow_end(refer,&mut visited) > clone.line{
+                     return None
+                 }
+             }
+         }
+         let target = match clone.target{
+             Some(ref target) => format!(" {}", target),
+             None => "".to_string(),
+         };
+         Some(format!("line {}: `{}.{}()`{} is the last use of `{}`\n\tAdivice: move `{}` instead of cloning it",
+                          clone.line, var, clone.method, target, var, var))
+     }
+
+     // Advise borrowing a parameter taken by value when the function only
+     // reads it and callers clone the values they pass, the clones would
+     // then be unnecessary
+     fn choice_borrow_param(&self,
+                                           func_name:&str,
+                                           index:usize,
+                                           param:Option<&summary::ParamSummary>) -> Option<String>
+     {
+         let borrowed = match self.fun_records.get(func_name).and_then(|info| info.params.get(index)){
+             Some(&pre_record::ParamInfo{ borrowed: Some(ref borrowed), .. }) => borrowed,
+             _ => return None,
+         };
+         match param{
+             Some(param) if !param.consumed() & !param.mutated => (),
+             _ => return None,
+         }
+         let mut calls = vec![];
+         self.collect_calls(func_name,&mut calls);
+         calls.sort_by(|a,b| a.call_location.cmp(&b.call_location));
+         let clones:Vec<String> = calls.iter().filter_map(|call| match call.cloned.get(index){
+             Some(&Some(ref var)) => Some(format!("line {} (`{}`)", call.call_location, var.join("."))),
+             _ => None,
+         }).collect();
+         if clones.is_empty(){
+             return None
+         }
+         Some(format!("\targument {} is only read by the function, it can be borrowed as `{}` instead and the clones on {} become unnecessary",
+                          index+1, borrowed, clones.join(", ")))
+     }
+
+     // Advise using the borrowed form instead of an owned conversion. A
+     // value passed to a function is only not needed when the function
+     // only reads its parameter, which can then borrow the string
+     fn choice_owned_conversion(&self,
+                                                 conversion:&ConversionInfo,
+                                                 summaries:&HashMap<String,FnSummary>) -> Option<String>
+     {
+         if let Some((ref func_name, index)) = conversion.param{
+             match param_summary(summaries,func_name,index){
+                 Some(param) if !param.consumed() & !param.mutated => (),
+                 _ => return None,
+             }
+             let borrowed = match self.fun_records.get(func_name).and_then(|info| info.params.get(index)){
+                 Some(&pre_record::ParamInfo{ borrowed: Some(ref borrowed), .. }) => borrowed,
+                 _ => return None,
+             };
+             return Some(format!("line {}: `{}` is {}, which only reads argument {}\n\tAdivice: let `{}` borrow it as `{}` and pass `{}`",
+                                     conversion.line, conversion.conversion, conversion.reason, index+1,
+                                     func_name, borrowed, conversion.borrowed))
+         }
+         Some(format!("line {}: `{}` is {}\n\tAdivice: use `{}` instead",
+                          conversion.line, conversion.conversion, conversion.reason, conversion.borrowed))
+     }
+
+     // Advise removing `mut` from bindings that are never changed, `&mut`
+     // from borrows that are only read, and `&mut` from parameters whose
+     // referent is never changed. A value lent to a function of the program
+     // is changed if the function changes its parameter, or any function
+     // the parameter is passed on to
+     fn choice_unneeded_mut(&self,
+                                          summaries:&HashMap<String,FnSummary>,
+                                          advice:&mut Vec<(usize,String)>)
+     {
+         let mut mutated = vec![];
+         let mut lent = vec![];
+         let mut bindings = vec![];
+         let mut borrows = vec![];
+         self.collect_mutability(&mut mutated,&mut lent,&mut bindings,&mut borrows);
+         let changed = |place:&Place| {
+             mutated.iter().any(|other| (other.var[0] == place.var[0]) & (other.decl == place.decl))
+                 | lent.iter().any(|&&(ref other, ref func_name, index)|
+                     (other.var[0] == place.var[0]) & (other.decl == place.decl)
+                         & param_summary(summaries,func_name,index).map_or(true, |param| param.mutated))
+         };
+         for binding in bindings{
+             if !changed(&binding.place){
+                 let var = &binding.place.var[0];
+                 advice.push((binding.line, format!("line {}: `{}` is declared `mut` but never changed\n\tAdivice: declare it with `let {}`",
+                                                    binding.line, var, var)));
+             }
+         }
+         for borrow in borrows{
+             match (&borrow.binding, &borrow.callee){
+                 (&Some(ref place), _) if !changed(place) =>
+                     advice.push((borrow.line, format!("line {}: `&mut {}` is only read through `{}`\n\tAdivice: borrow it with `&{}`",
+                                                        borrow.line, borrow.borrowed, place.var[0], borrow.borrowed))),
+                 (_, &Some((ref func_name, index))) => {
+                     if let Some(param) = param_summary(summaries,func_name,index){
+                         if !param.mutated{
+                             advice.push((borrow.line, format!("line {}: `&mut {}` is never changed by `{}`\n\tAdivice: pass `&{}` once argument {} of `{}` is a shared reference",
+                                                                borrow.line, borrow.borrowed, func_name, borrow.borrowed, index+1, func_name)));
+                         }
+                     }
+                 },
+                 _ => (),
+             }
+         }
+         let mut names:Vec<&String> = self.fun_records.keys().collect();
+         names.sort();
+         for name in names{
+             let info = &self.fun_records[name];
+             let mut index = 0;
+             while index < info.params.len(){
+                 let param = &info.params[index];
+                 if let (&Some(ref shared), Some(summary)) = (&param.shared, param_summary(summaries,name,index)){
+                     if !summary.mutated{
+                         advice.push((0, format!("{}:\n\targument {} (`{}`) is never changed through the reference, it can be borrowed as `{}`",
+                                                  name, index+1, param.name, shared)));
+                     }
+                 }
+                 index+=1;
+             }
+         }
+     }
+
+     // Advise returning a reference from a function that only returns clones
+     // of fields of its borrowed receiver or of a borrowed parameter. The
+     // callers that keep the result and move or change it need an owned
+     // value, and clone the reference instead
+     fn choice_return_reference(&self, cfgs:&Vec<&Cfg>, advice:&mut Vec<String>){
+         let mut returns = vec![];
+         let mut uses = vec![];
+         self.collect_returns(&mut returns,&mut uses);
+         let mut mutated = vec![];
+         let mut lent = vec![];
+         let mut bindings = vec![];
+         let mut borrows = vec![];
+         self.collect_mutability(&mut mutated,&mut lent,&mut bindings,&mut borrows);
+         let mut names:Vec<&String> = returns.iter().map(|info| &info.function).collect();
+         names.sort();
+         names.dedup();
+         for name in names{
+             let fun_returns:Vec<&&ReturnInfo> = returns.iter().filter(|info| &info.function == name).collect();
+             if fun_returns.iter().any(|info| info.field.is_none()){
+                 continue
+             }
+             let info = match self.fun_records.get(name){
+                 Some(info) => info,
+                 None => continue,
+             };
+             let borrowed = match info.borrowed_output{
+                 Some(ref borrowed) => borrowed,
+                 None => continue,
+             };
+             let mut fields:Vec<String> = fun_returns.iter().filter_map(|info| info.field.clone()).collect();
+             fields.sort();
+             fields.dedup();
+             let lines:Vec<usize> = fun_returns.iter().map(|info| info.line).collect();
+             // A reference returned from a method taking &self, or from a
+             // function with a single borrowed parameter, gets its lifetime
+             // by elision
+             let refs:Vec<&String> = info.params.iter().filter(|param| match param.ty{
+                 Ty::Ref => true,
+                 _ => false,
+             }).map(|param| &param.name).collect();
+             let lifetime = match info.receiver{
+                 Some(Mode::Ref) | Some(Mode::MutRef) => "the lifetime is elided to the one of `self`".to_string(),
+                 _ if refs.len() == 1 => format!("the lifetime is elided to the one of `{}`",refs[0]),
+                 _ => "the lifetime of the borrowed parameter needs to be named".to_string(),
+             };
+             advice.push(format!("{}:\n\treturns a clone of `{}` on line {}\n\tAdivice: return `{}` instead, {}",
+                                  name, fields.join("`, `"), join_lines(&lines), borrowed, lifetime));
+
+             // A caller needs an owned value if it gives the result away, or
+             // if the variable the result is bound to is moved or changed
+             let owned_lines:Vec<usize> = uses.iter().filter(|result| &result.function == name).filter(|result| match result.binding{
+                 Some(ref place) => moved_in(cfgs,place) | mutated.iter().any(|other| (other.var[0] == place.var[0]) & (other.decl == place.decl)),
+                 None => true,
+             }).map(|result| result.line).collect();
+             let mut calls = vec![];
+             self.collect_calls(name,&mut calls);
+             let mut owned = vec![];
+             let mut shared = vec![];
+             for call in calls{
+                 if owned_lines.contains(&call.call_location){
+                     owned.push(call.call_location);
+                 }else{
+                     shared.push(call.call_location);
+                 }
+             }
+             owned.sort();
+             owned.dedup();
+             shared.sort();
+             shared.dedup();
+             if !owned.is_empty(){
+                 advice.push(format!("\tthe calls on line {} need an owned value, clone the reference there", join_lines(&owned)));
+             }
+             if !shared.is_empty(){
+                 advice.push(format!("\tthe calls on line {} can use the reference", join_lines(&shared)));
+             }
+             if owned.is_empty() & shared.is_empty(){
+                 advice.push("\tthe function is not called".to_string());
+             }
+         }
+     }
+
+     // Report how the fields of each structure built by the program hold
+     // their values. An owned field that is never changed and always
+     // initialised from a clone can borrow the data instead, and hold
+     // either through Cow when only some values are clones. A reference
+     // field that borrows values created only for it ties the structure to
+     // them and can own them instead
+     fn choice_field_ownership(&self, advice:&mut Vec<String>){
+         let mut inits = vec![];
+         let mut writes = vec![];
+         self.collect_fields(&mut inits,&mut writes);
+         let mut names:Vec<&String> = self.structure_list.keys().collect();
+         names.sort();
+         for name in names{
+             let struct_inits:Vec<&&FieldInit> = inits.iter().filter(|init| &init.structure == name).collect();
+             if struct_inits.is_empty(){
+                 continue
+             }
+             let mut fields:Vec<(&String,&Ty)> = self.structure_list[name].iter().collect();
+             fields.sort_by(|a,b| a.0.cmp(b.0));
+             let mut lines = vec![];
+             let mut only_read = vec![];
+             for (field, ty) in fields{
+                 let written = writes.iter().any(|&&(ref structure, ref other)| (structure == name) & (other == field));
+                 if !written{
+                     only_read.push(format!("`{}`",field));
+                 }
+                 let form = match self.field_forms.get(name).and_then(|forms| forms.get(field)){
+                     Some(form) => form,
+                     None => continue,
+                 };
+                 let field_inits:Vec<&&&FieldInit> = struct_inits.iter().filter(|init| &init.field == field).collect();
+                 match ty{
+                     &Ty::NonPrimitive if !written & !field_inits.is_empty() => {
+                         let clones:Vec<String> = field_inits.iter().filter_map(|init| init.cloned.as_ref()
+                                             .map(|cloned| format!("`{}` on line {}", cloned, init.line))).collect();
+                         if clones.len() == field_inits.len(){
+                             lines.push(format!("\t`{}` is always initialised from a clone ({}) and never changed\n\tAdivice: borrow the data as `&'a {}`",
+                                                field, clones.join(", "), form));
+                         }else if !clones.is_empty(){
+                             let owned:Vec<usize> = field_inits.iter().filter(|init| init.cloned.is_none()).map(|init| init.line).collect();
+                             lines.push(format!("\t`{}` is initialised from a clone ({}) and from an owned value on line {}, and never changed\n\tAdivice: hold either as `Cow<'a, {}>`",
+                                                field, clones.join(", "), join_lines(&owned), form));
+                         }
+                     },
+                     &Ty::Ref => {
+                         let temporaries:Vec<usize> = field_inits.iter().filter(|init| init.temporary).map(|init| init.line).collect();
+                         if !temporaries.is_empty(){
+                             lines.push(format!("\t`{}` borrows a value created only to be borrowed on line {}, which the structure cannot outlive\n\tAdivice: own the value as `{}`",
+                                                field, join_lines(&temporaries), form));
+                         }
+                     },
+                     _ => (),
+                 }
+             }
+             if !only_read.is_empty(){
+                 lines.push(format!("\t{} only ever read after the structure is built", if only_read.len() == 1 {
+                     format!("{} is",only_read[0])
+                 }else{
+                     format!("{} are",only_read.join(", "))
+                 }));
+             }
+             if !lines.is_empty(){
+                 advice.push(format!("{}:",name));
+                 advice.extend(lines);
+             }
+         }
+     }
+
+     // Advise against passing or returning large values by value when the
+     // function is called in a loop or calls itself, since each call copies
+     // the whole value. A parameter that is only read can be borrowed, one
+     // that is changed can be changed in place, and a boxed value moves
+     // only a pointer
+     fn choice_large_values(&self,
+                                          cfgs:&Vec<&Cfg>,
+                                          summaries:&HashMap<String,FnSummary>,
+                                          advice:&mut Vec<String>)
+     {
+         let mut names:Vec<&String> = self.fun_records.keys().collect();
+         names.sort();
+         for name in names{
+             let info = &self.fun_records[name];
+             let mut calls = vec![];
+             self.collect_calls(name,&mut calls);
+             calls.sort_by(|a,b| a.call_location.cmp(&b.call_location));
+             let hot:Vec<String> = calls.iter().filter_map(|call| {
+                 if call.caller.as_ref() == Some(name){
+                     Some(format!("line {} (recursive call)", call.call_location))
+                 }else if in_loop(cfgs,call.caller.as_ref(),call.call_location){
+                     Some(format!("line {} (in a loop)", call.call_location))
+                 }else{
+                     None
+                 }
+             }).collect();
+             if hot.is_empty(){
+                 continue
+             }
+             let mut lines = vec![];
+             let mut index = 0;
+             while index < info.params.len(){
+                 let param = &info.params[index];
+                 if let Some(&(ref ty, size)) = param.value_size.as_ref(){
+                     if size > LARGE_SIZE{
+                         let rewrite = match param_summary(summaries,name,index){
+                             Some(summary) if !summary.consumed() & !summary.mutated => format!("take it as `&{}`", ty),
+                             Some(summary) if !summary.consumed() => format!("take it as `&mut {}` and change it in place", ty),
+                             _ => format!("pass it as `Box<{}>` so that only a pointer is moved", ty),
+                         };
+                         lines.push(format!("\targument {} (`{}`) is about {} bytes and is copied on each call\n\tAdivice: {}",
+                                            index+1, param.name, size, rewrite));
+                     }
+                 }
+                 index+=1;
+             }
+             if let Some(&(ref ty, size)) = info.output_size.as_ref(){
+                 if size > LARGE_SIZE{
+                     lines.push(format!("\tthe returned value is about {} bytes and is copied on each call\n\tAdivice: let the caller pass a `&mut {}` to fill in place, or return `Box<{}>`",
+                                        size, ty, ty));
+                 }
+             }
+             if !lines.is_empty(){
+                 advice.push(format!("{}: called on {}", name, hot.join(", ")));
+                 advice.extend(lines);
+             }
+         }
+     }
+
+     // Explain how an argument declared as a generic type parameter is
+     // passed according to the bounds of the parameter.
+     // Return None when the argument is copied instead of moved
+     fn generic_note(&self, func_name:&str, index:usize) -> Option<String>{
+         if let Some(info) = self.fun_records.get(func_name){
+             if let Some(param) = info.params.get(index){
+                 if let Some(ref generic) = param.generic{
+                     if pre_record::has_bound(generic,"Copy",&info.generics){
+                         return None
+                     }
+                     if pre_record::has_bound(generic,"Clone",&info.generics){
+                         return Some(format!("(generic `{}: Clone`, a caller that still needs the value can pass a clone)",generic))
+                     }
+                     return Some(format!("(generic `{}`)",generic))
+                 }
+             }
+         }
+         Some("".to_string())
+     }
+
      // Check each function call of a specific function.
-     // If onw of a perameter's coorespond argument is used after the
-     // call, then its place in print will be recorded as false.
+     // If onw of a perameter's coorespond argument is still needed after
+     // the call on some path, then its place in print will be recorded as false.
      fn check_function_call(&self, 
                                             func_name:&str,
                                             length:usize,
@@ -150,11 +856,9 @@ impl <'a> SymbolTable<'a>{
         if let Some(call_infos) = self.call_records.get(func_name){
             //Each call for a specific function 
             for info in call_infos{
-                let call_loc = info.call_location; 
                 let mut index = 0;
                 while index < length{
-                    let arg_last_used = self.get_last_used(&info.arguments[index]);
-                    if arg_last_used != call_loc{
+                    if let Some(&true) = info.live_after.get(index){
                         print[index] = false;
                     }
                     index+=1;
@@ -166,6 +870,370 @@ impl <'a> SymbolTable<'a>{
         }
     }
 
+     // Generate drop advice for each field of a partially moved tuple or
+     // structure. Fields that are not moved can be dropped where they are
+     // no longer needed (or after the declaration if the value is never used)
+     fn choice_one_drop_fields(&self,
+                                      var: &str,
+                                      info: &VarInfo,
+                                      print_list:&mut HashMap<usize,Vec<String>>)
+     {
+          if let Some(ref structure) = info.structure{
+              for (index, element) in structure{
+                  match element.var_type{
+                      Ty::NonPrimitive => (),
+                      _ => continue,
+                  }
+                  if (element.moved != MoveState::Init) | !element.ref_by.is_empty(){
+                      continue
+                  }
+                  let mut lines = element.drop_points.clone();
+                  if element.last_used_loc.or(info.last_used_loc).is_none(){
+                      lines.push(element.decl_loc+1);
+                  }
+                  for line in lines{
+                      if line < element.when_to_drop{
+                          add_advice(print_list, line,
+                                          "drop( ".to_string()+ var + "." + index +
+                                          " ); // Adivice: a drop function can add here");
+                      }
+                  }
+              }
+          }
+     }
+
+     // Generate advice for the structures and tuples of which only some
+     // fields are moved out
+     fn choice_partial_move(&self, advice:&mut Vec<String>){
+          let mut vars:Vec<&String> = self.var_table.keys().collect();
+          vars.sort();
+          for var in vars{
+              let info = &self.var_table[var];
+              if info.moved != MoveState::Init{
+                  continue
+              }
+              if let Some(ref structure) = info.structure{
+                  // Each moved field with the line it is first moved on
+                  let mut moved = vec![];
+                  let mut remaining = vec![];
+                  for (field, element) in structure{
+                      if element.moved != MoveState::Init{
+                          let line = element.move_lines.first().cloned().unwrap_or(info.decl_loc);
+                          moved.push((line, field.clone()));
+                      }else{
+                          match element.var_type{
+                              Ty::NonPrimitive => remaining.push(field.clone()),
+                              _ => (),
+                          }
+                      }
+                  }
+                  if moved.is_empty(){
+                      continue
+                  }
+                  moved.sort();
+                  remaining.sort();
+                  let moved_fields:Vec<String> = moved.iter().map(|&(_, ref field)| field.clone()).collect();
+                  advice.push(format!("line {}: `{}` is partially moved, {} moved out",
+                                          moved[0].0, var, field_list(var,&moved_fields)));
+                  if let Some(pattern) = self.destructure_pattern(var,info,structure){
+                      advice.push(format!("\t\tAdivice: destructure `{}` with `{}` so that each field is owned by a variable of its own",
+                                              var, pattern));
+                  }
+                  if !remaining.is_empty(){
+                      advice.push(format!("\t\tAdivice: {} still owned by `{}`, drop each one as soon as it is no longer needed",
+                                              field_list(var,&remaining), var));
+                  }
+                  // A field can only be taken out of a variable declared
+                  // `let mut`, and only if its type leaves a value behind
+                  let mutable = self.mut_bindings.iter().any(|binding|
+                      (binding.place.var.len() == 1) && String::eq(&binding.place.var[0],var) && (binding.place.decl == info.decl_loc));
+                  if !mutable{
+                      continue
+                  }
+                  let types = info.type_name.as_ref().and_then(|name| self.field_types.get(name));
+                  for &(line, ref field) in &moved{
+                      let place = format!("{}.{}",var,field);
+                      let rewritten = types.and_then(|types| types.get(field))
+                                           .and_then(|ty| self.take_rewrite(&place,ty));
+                      if let Some(rewritten) = rewritten{
+                          advice.push(format!("\t\tAdivice: on line {}, take the value with `{}` instead, which leaves another value behind and keeps `{}` whole",
+                                                  line, rewritten, var));
+                      }
+                  }
+              }
+          }
+          for scope in &self.enclose_scope{
+              scope.choice_partial_move(advice);
+          }
+     }
+
+     // The `let` statement that destructures a variable into a variable per
+     // field, for example: `let Person { name, age } = p;`.
+     // None if the name of the structure is not known
+     fn destructure_pattern(&self, var:&str, info:&VarInfo, structure:&HashMap<String,VarInfo>) -> Option<String>{
+          let mut fields:Vec<String> = match info.type_name.as_ref().and_then(|name| self.structure_list.get(name)){
+              Some(fields) => fields.keys().cloned().collect(),
+              None => structure.keys().cloned().collect(),
+          };
+          if fields.iter().all(|field| field.parse::<usize>().is_ok()){
+              fields.sort_by_key(|field| field.parse::<usize>().unwrap_or(0));
+              let names:Vec<String> = fields.iter().map(|field| format!("{}_{}",var,field)).collect();
+              let name = info.type_name.clone().unwrap_or_default();
+              return Some(format!("let {}({}) = {};",name,names.join(", "),var))
+          }
+          fields.sort();
+          match info.type_name{
+              Some(ref name) => Some(format!("let {} {{ {} }} = {};",name,fields.join(", "),var)),
+              None => None,
+          }
+     }
+
+     // Only some fields of the variable are borrowed. The borrows are
+     // disjoint, so the other fields can still be moved or dropped while
+     // the borrowing variables are in use
+     fn choice_disjoint_borrow(&self,
+                                      var: &str,
+                                      info: &VarInfo,
+                                      field_refs: &Vec<(String,Vec<String>)>,
+                                      print_list:&mut HashMap<usize,Vec<String>>)
+     {
+          let mut free = vec![];
+          if let Some(ref structure) = info.structure{
+              for (field, element) in structure{
+                  let path = var.to_string() + "." + field;
+                  let borrowed = field_refs.iter().any(|&(ref borrowed,_)|
+                                      (*borrowed == path) | borrowed.starts_with(&(path.clone() + ".")));
+                  match element.var_type{
+                      Ty::NonPrimitive => if !borrowed & (element.moved == MoveState::Init){
+                          free.push("`".to_string() + &path + "`");
+                      },
+                      _ => (),
+                  }
+              }
+          }
+          if free.is_empty(){
+              return
+          }
+          free.sort();
+          for &(ref borrowed, ref refer) in field_refs{
+              if let Some(info) = self.find_var_info(&refer[0]){
+                  add_advice(print_list, info.decl_loc,
+                                  format!("// Adivice: `{}` borrows `{}` only, {} can still be moved or dropped while `{}` is in use",
+                                              refer.join("."), borrowed, free.join(", "), refer.join(".")));
+              }
+          }
+     }
+
+     // Get the information of a variable declared in this scope or in one
+     // of its enclosing scopes
+     fn find_var_info(&self, var:&str) -> Option<&VarInfo>{
+         if let Some(info) = self.var_table.get(var){
+             return Some(info)
+         }
+         for scope in &self.enclose_scope{
+             if let Some(info) = scope.find_var_info(var){
+                 return Some(info)
+             }
+         }
+         None
+     }
+
+     // The line on which a borrow ends: when the borrowing variable gets out
+     // of scope, or later if the reference is copied, reborrowed or
+     // borrowed itself by another variable that lives longer
+     fn borrow_end(&self, refer:&Vec<String>, visited:&mut Vec<String>) -> usize{
+         if visited.contains(&refer[0]){
+             return 0
+         }
+         visited.push(refer[0].clone());
+         let info = match self.find_var_info(&refer[0]){
+             Some(info) => info,
+             None => return 0,
+         };
+         let mut when_drop = info.when_to_drop;
+         let mut derived = info.ref_by.clone();
+         if let Some(ref structure) = info.structure{
+             let mut field_refs = vec![];
+             field_borrows(&refer[0],structure,&mut field_refs);
+             derived.extend(field_refs.into_iter().map(|(_,refer)| refer));
+         }
+         for refer in &derived{
+             let tmp = self.borrow_end(refer,visited);
+             if when_drop < tmp { when_drop = tmp }
+         }
+         when_drop
+     }
+
+     // The last line on which a variable is still needed, None if it is
+     // needed until the end of the function
+     fn live_end(&self, info:&VarInfo) -> Option<usize>{
+         let mut end = match info.drop_points.iter().max(){
+             Some(line) => line-1,
+             None => match info.last_used_loc{
+                 Some(_) => return None,
+                 None => info.decl_loc,
+             },
+         };
+         if let Some(line) = info.last_used_loc{
+             if end < line { end = line }
+         }
+         Some(end)
+     }
+
+     // The last line of the region of a variable: the value is needed
+     // until its last use and until the last use of every reference
+     // derived from it
+     fn region_end(&self, var:&str, visited:&mut Vec<String>) -> Option<usize>{
+         if visited.iter().any(|v| v == var){
+             return Some(0)
+         }
+         visited.push(var.to_string());
+         let info = match self.find_var_info(var){
+             Some(info) => info,
+             None => return Some(0),
+         };
+         let mut end = match self.live_end(info){
+             Some(end) => end,
+             None => return None,
+         };
+         let mut derived = info.ref_by.clone();
+         if let Some(ref structure) = info.structure{
+             let mut field_refs = vec![];
+             field_borrows(var,structure,&mut field_refs);
+             derived.extend(field_refs.into_iter().map(|(_,refer)| refer));
+         }
+         for refer in &derived{
+             match self.region_end(&refer[0],visited){
+                 Some(tmp) => if end < tmp { end = tmp },
+                 None => return None,
+             }
+         }
+         Some(end)
+     }
+
+     // Move a line that is inside a block enclosed in this scope to the
+     // last line of that block, so that a range ending on it covers whole
+     // statements
+     fn statement_end(&self, line:usize) -> usize{
+         let mut line = line;
+         let mut changed = true;
+         while changed{
+             changed = false;
+             for scope in &self.enclose_scope{
+                 if (scope.scope_start <= line) & (line < scope.scope_end){
+                     line = scope.scope_end;
+                     changed = true;
+                 }
+             }
+         }
+         line
+     }
+
+     // Generate advice for the owned values that are dropped at the end of
+     // their scope long after they are last needed. The statements from
+     // their declaration to the end of their region can be wrapped in an
+     // inner block, or the declaration can be moved closer to its first use
+     fn choice_narrow_scope(&self, cfgs:&Vec<&Cfg>, advice:&mut Vec<(usize,String)>){
+         let mut ranges:Vec<(usize,usize)> = vec![];
+         for (var,info) in self.sorted_vars(){
+             match info.var_type{
+                 Ty::NonPrimitive => (),
+                 _ => continue,
+             }
+             if (info.moved != MoveState::Init) | (info.decl_loc <= self.scope_start){
+                 continue
+             }
+             if let Some(end) = self.region_end(var,&mut vec![]){
+                 let end = self.statement_end(end);
+                 if end+1 < self.scope_end{
+                     ranges.push((info.decl_loc,end));
+                 }
+             }
+         }
+         ranges.sort();
+
+         // Overlapping ranges are wrapped in the same block. Variables
+         // declared inside the range have to be dead at its end as well
+         let mut merged:Vec<(usize,usize)> = vec![];
+         for range in ranges{
+             if let Some(last) = merged.last_mut(){
+                 if range.0 <= last.1{
+                     if last.1 < range.1 { last.1 = range.1 }
+                     continue
+                 }
+             }
+             merged.push(range);
+         }
+         let mut last_end = 0;
+         for (start,end) in merged{
+             if start <= last_end{
+                 continue
+             }
+             let mut end = end;
+             let mut valid = true;
+             let mut changed = true;
+             while changed & valid{
+                 changed = false;
+                 for (var,info) in &self.var_table{
+                     if (info.decl_loc < start) | (info.decl_loc > end){
+                         continue
+                     }
+                     match self.region_end(var,&mut vec![]){
+                         Some(tmp) => {
+                             let tmp = self.statement_end(tmp);
+                             if end < tmp{
+                                 end = tmp;
+                                 changed = true;
+                             }
+                         },
+                         None => valid = false,
+                     }
+                 }
+             }
+             if !valid | (end+1 >= self.scope_end){
+                 continue
+             }
+             last_end = end;
+             let mut owners:Vec<String> = self.var_table.iter()
+                             .filter(|&(_,info)| (info.decl_loc >= start) & (info.decl_loc <= end)
+                                                     & (info.moved == MoveState::Init)
+                                                     & match info.var_type { Ty::NonPrimitive => true, _ => false })
+                             .map(|(var,_)| "`".to_string() + var + "`").collect();
+             owners.sort();
+             advice.push((start,format!("line {}-{}: wrap these lines in `{{ ... }}` so that {} {} dropped after line {} instead of at the end of the scope on line {}",
+                                         start, end, owners.join(", "),
+                                         if owners.len() == 1 { "is" } else { "are" },
+                                         end, self.scope_end)));
+         }
+
+         for (var,info) in self.sorted_vars(){
+             match info.var_type{
+                 Ty::Primitive => continue,
+                 _ => (),
+             }
+             // A parameter cannot be declared later than the function
+             if self.params.iter().any(|param| (param.decl == info.decl_loc) & (param.var[0] == *var)){
+                 continue
+             }
+             if let Some(first) = first_use(cfgs,var,info.decl_loc){
+                 let first = statement_start(cfgs,info.decl_loc,first);
+                 if (first > info.decl_loc+MIN_GAP) & !init_changed(cfgs,var,info.decl_loc,first){
+                     let what = match info.var_type{
+                         Ty::Ref => format!("the borrow of `{}` then starts on line {}",
+                                             info.ref_to.as_ref().map(|v| v.join(".")).unwrap_or_else(|| var.to_string()), first),
+                         _ => format!("`{}` then lives from line {} only", var, first),
+                     };
+                     advice.push((info.decl_loc,format!("line {}: move the declaration of `{}` to just before line {}, where it is first used; {}",
+                                                         info.decl_loc, var, first, what)));
+                 }
+             }
+         }
+
+         for scope in &self.enclose_scope{
+             scope.choice_narrow_scope(cfgs,advice);
+         }
+     }
+
      // Generate advice for function drop.
      // Check whether the given varibale can be dropped earlier, if can,
      // put it into the print list
@@ -175,36 +1243,30 @@ impl <'a> SymbolTable<'a>{
                                       mut print_list:&mut HashMap<usize,Vec<String>>) 
      {
           if  self.no_var_ref(var){
-              match info.last_used_loc{
-                  Some(line) => {
-                      let mut can_print = true;
-                      if (line+1) == info.when_to_drop{
-                          can_print = false
-                      } 
-                
-                      if can_print{
-                          let mut found = false;
-                          if let Some(print_line) = print_list.get_mut(&(line+1)){
-                              found = true;
-                              print_line.push("drop( ".to_string()+ var + 
-                                                          " ); // Adivice: a drop function can add here");
-                          }
-                            
-                          if ! found {
-                              print_list.insert(line+1,vec!["drop( ".to_string()+ var + " ); // Adivice: a drop function can add here"]);
-                          }
-                      }      
+              // One drop for each path on which the value is no longer needed,
+              // unless the value goes out of scope there anyway
+              for line in &info.drop_points{
+                  if *line < info.when_to_drop{
+                      add_advice(print_list, *line,
+                                      "drop( ".to_string()+ var + " ); // Adivice: a drop function can add here");
                   }
-                  None => (),
-              }  
+              }
           } else{
               // If the variable is reffed by another variable, check when will the reference
               // be dropped
+              // Borrows of a field keep the whole variable alive as well
+              let mut field_refs = vec![];
+              if let Some(ref structure) = info.structure{
+                  field_borrows(var,structure,&mut field_refs);
+              }
               let mut when_drop = 0;
-              for  refer in &info.ref_by{
-                  let tmp = self.get_when_drop(&refer,var);
+              for  refer in info.ref_by.iter().chain(field_refs.iter().map(|&(_,ref refer)| refer)){
+                  let tmp = self.borrow_end(refer,&mut vec![]);
                   if when_drop< tmp { when_drop = tmp } 
               }
+              if info.ref_by.is_empty(){
+                  self.choice_disjoint_borrow(var,info,&field_refs,print_list);
+              }
               // If the references will be drop earlier than the variable
               if  (when_drop != 0) & (when_drop < info.when_to_drop){
                   let mut found = false;
@@ -222,3 +1284,191 @@ impl <'a> SymbolTable<'a>{
           }
       }
 }    
+
+// Print the values of a function that are used after being moved, used as
+// a whole after a field is moved, moved on only some paths or inside a
+// loop, or that need a drop flag.
+// Return whether anything is printed
+fn print_move_analysis(analysis:&MoveAnalysis) -> bool{
+    if analysis.moved_uses.is_empty() & analysis.partial_uses.is_empty()
+        & analysis.conditional.is_empty() & analysis.loop_moves.is_empty() & analysis.drop_flags.is_empty(){
+        return false
+    }
+    println!("{}:",analysis.name);
+    for moved_use in &analysis.moved_uses{
+        // Explained below together with the loop
+        if analysis.loop_moves.iter().any(|m| (m.place == moved_use.place) & (m.line == moved_use.line)){
+            continue
+        }
+        let var = moved_use.place.var.join(".");
+        if moved_use.maybe{
+            println!("\tline {}: `{}` may have been moved on line {}, depending on the path taken",
+                         moved_use.line, var, join_lines(&moved_use.move_lines));
+        }else{
+            println!("\tline {}: `{}` is used after it is moved on line {}",
+                         moved_use.line, var, join_lines(&moved_use.move_lines));
+        }
+    }
+    for partial_use in &analysis.partial_uses{
+        let var = partial_use.place.var.join(".");
+        let fields:Vec<String> = partial_use.fields.iter().map(|field| "`".to_string() + &field.var.join(".") + "`").collect();
+        println!("\tline {}: `{}` is used as a whole after {} is moved out on line {}",
+                     partial_use.line, var, fields.join(", "), join_lines(&partial_use.move_lines));
+        println!("\t\tAdivice: move the field out with `std::mem::take` (or `Option::take`) instead, or clone it, so that `{}` stays whole",
+                     var);
+    }
+    for loop_move in &analysis.loop_moves{
+        let var = loop_move.place.var.join(".");
+        let how = if loop_move.maybe { "may no longer hold" } else { "no longer holds" };
+        println!("\tline {}: `{}` is moved on line {} inside the loop on line {}-{}",
+                     loop_move.line, var, join_lines(&loop_move.move_lines), loop_move.start, loop_move.end);
+        println!("\t\tthe first iteration moves it out, so on the next iteration `{}` {} a value on line {}",
+                     var, how, loop_move.line);
+        if loop_move.place.decl < loop_move.start{
+            println!("\t\tAdivice: clone `{}` before moving it, pass a reference (`&{}`) if it is only read, or declare `{}` inside the loop so that each iteration gets its own value",
+                         var, var, var);
+        }else{
+            println!("\t\tAdivice: clone `{}` before moving it, or pass a reference (`&{}`) if it is only read",
+                         var, var);
+        }
+    }
+    for conditional in &analysis.conditional{
+        let var = conditional.place.var.join(".");
+        if conditional.join_line == 0{
+            println!("\t`{}` is moved on line {} on only some paths, which join at the end of the function",
+                         var, join_lines(&conditional.move_lines));
+        }else{
+            println!("\t`{}` is moved on line {} on only some paths, which join before line {}",
+                         var, join_lines(&conditional.move_lines), conditional.join_line);
+        }
+        println!("\t\tAdivice: clone `{}` in the branch that moves it, or keep it in an Option and move it out with `{}.take()`",
+                     var, var);
+    }
+    for place in &analysis.drop_flags{
+        println!("\t`{}` needs a drop flag: it is dropped at the end of its scope only if it is not moved",
+                     place.var.join("."));
+    }
+    true
+}
+
+// The summary of the parameter with the given index of a function
+fn param_summary<'b>(summaries:&'b HashMap<String,FnSummary>,
+                                func_name:&str,
+                                index:usize) -> Option<&'b summary::ParamSummary>
+{
+    match summaries.get(func_name){
+        Some(summary) => summary.params.get(index),
+        None => None,
+    }
+}
+
+// Lines that must be between a declaration and the first use of the
+// variable before moving the declaration is suggested
+const MIN_GAP: usize = 2;
+
+// The first line on which a variable declared on the given line is used
+fn first_use(cfgs:&Vec<&Cfg>, var:&str, decl:usize) -> Option<usize>{
+    let mut first = None;
+    for cfg in cfgs{
+        for block in &cfg.blocks{
+            for point in &block.points{
+                if (point.action != Action::Def) & (point.place.decl == decl)
+                    & (point.place.var[0] == var) & (point.line > decl){
+                    first = match first{
+                        Some(line) if line <= point.line => Some(line),
+                        _ => Some(point.line),
+                    };
+                }
+            }
+        }
+    }
+    first
+}
+
+// The line a statement containing the given line starts on: the first line
+// of the outermost branch or loop around it that starts after the
+// declaration
+fn statement_start(cfgs:&Vec<&Cfg>, decl:usize, line:usize) -> usize{
+    let mut start = line;
+    for cfg in cfgs{
+        for region in &cfg.regions{
+            if (region.start > decl) & (region.start < start) & (region.end >= line){
+                start = region.start;
+            }
+        }
+    }
+    start
+}
+
+// Whether a variable read by the initializer of a declaration is assigned,
+// moved or used between the declaration and the given line. Moving the
+// declaration would then give the variable another value
+fn init_changed(cfgs:&Vec<&Cfg>, var:&str, decl:usize, line:usize) -> bool{
+    for cfg in cfgs{
+        let mut read = vec![];
+        for block in &cfg.blocks{
+            for point in &block.points{
+                if (point.line == decl) & (point.action != Action::Def) & (point.place.var[0] != var){
+                    read.push(point.place.clone());
+                }
+            }
+        }
+        for block in &cfg.blocks{
+            for point in &block.points{
+                if (point.line > decl) & (point.line < line)
+                    & read.iter().any(|place| (place.decl == point.place.decl) & (place.var[0] == point.place.var[0])){
+                    return true
+                }
+            }
+        }
+    }
+    false
+}
+
+// Collect the fields of a variable that are borrowed, together with the
+// variables borrowing them
+fn field_borrows(var:&str,
+                        structure:&HashMap<String,VarInfo>,
+                        field_refs:&mut Vec<(String,Vec<String>)>)
+{
+    for (field, info) in structure{
+        let path = var.to_string() + "." + field;
+        for refer in &info.ref_by{
+            field_refs.push((path.clone(),refer.clone()));
+        }
+        if let Some(ref structure) = info.structure{
+            field_borrows(&path,structure,field_refs);
+        }
+    }
+}
+
+// The fields of a variable as a list, for example: `p.x`, `p.y`
+fn field_list(var:&str, fields:&[String]) -> String{
+    let names:Vec<String> = fields.iter().map(|field| format!("`{}.{}`", var, field)).collect();
+    if names.len() == 1{
+        names[0].clone() + " is"
+    }else{
+        names.join(", ") + " are"
+    }
+}
+
+// Whether a line of a function is inside a loop of the function
+fn in_loop(cfgs:&Vec<&Cfg>, function:Option<&String>, line:usize) -> bool{
+    cfgs.iter().filter(|cfg| Some(&cfg.name) == function).any(|cfg| cfg.regions.iter().any(|region|
+        (region.kind == RegionKind::Loop) & (region.start <= line) & (line <= region.end)))
+}
+
+// Whether the value of a place is moved somewhere in the function
+fn moved_in(cfgs:&Vec<&Cfg>, place:&Place) -> bool{
+    cfgs.iter().any(|cfg| cfg.blocks.iter().any(|block| block.points.iter().any(|point|
+        (point.action == Action::Move) & (point.place.var[0] == place.var[0]) & (point.place.decl == place.decl))))
+}
+
+fn join_lines(lines:&[usize]) -> String{
+    lines.iter().map(|line| line.to_string()).collect::<Vec<String>>().join(", ")
+}
+
+// Add a line of advice which will be printed before the given line
+fn add_advice(print_list:&mut HashMap<usize,Vec<String>>, line:usize, advice:String){
+    print_list.entry(line).or_insert_with(Vec::new).push(advice);
+}
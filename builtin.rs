@@ -1,70 +1,359 @@
 // File: Rust built-in function checker
-// Purpose: Take the input function name to match with built-in 
-//                 function of Rust that is recorded and return the cooresponding
-//                 return type.
+// Purpose: Take the input function name to match with built-in
+//                 function of Rust that is recorded in the signature database
+//                 and return the cooresponding return type.
 // Author : Ziling Zhou (802414)
 
-#[derive(Debug,Clone)]
+use std::collections::HashMap;
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use std::collections::BTreeMap;
+
+use serialize::json::{self,Json};
+
+#[derive(Debug,Clone,PartialEq)]
 pub enum Ty{
     Ref,
     NonPrimitive,
     Primitive
 }
 
-// Entry point of this file
-// Take the function name and receiver, call the relative
-// function depends on type of receiver in order to get the
-// return type of tunction  
-pub fn get_func_rety( func_name : &str, var_ty : Option<Ty>) ->Ty{
-    if let Some(var_type) = var_ty{
-        match var_type {
-            Ty::Primitive =>  get_prim_func_rety(func_name),
-            Ty::NonPrimitive =>get_non_prim_func_rety(func_name),
-            Ty::Ref=>get_ref_func_rety(func_name),
+// Version of the signature database format understood by this tool
+pub const SIGNATURE_VERSION: u32 = 1;
+
+// The signature database shipped with the tool
+const SHIPPED_SIGNATURES: &'static str = include_str!("signatures.json");
+
+// File in the working directory that import-signatures writes by default,
+// it overrides shipped signatures
+pub const IMPORTED_SIGNATURES: &'static str = "advisor_imported_signatures.json";
+
+// File in the working directory that overrides shipped and imported
+// signatures
+const USER_SIGNATURES: &'static str = "advisor_signatures.json";
+
+// How a receiver or an argument is passed to a function
+#[derive(Debug,Clone,PartialEq)]
+pub enum Mode{
+    Value,
+    Ref,
+    MutRef,
+}
+
+// Signature of a builtin method or function
+#[derive(Debug,Clone)]
+pub struct Signature{
+    // Type the function belongs to, empty for free functions
+    pub self_ty: String,
+    pub name: String,
+    // None for functions that do not have a receiver
+    pub receiver: Option<Mode>,
+    pub params: Vec<Mode>,
+    // None when the return type is generic, for example: Option::unwrap.
+    // The returned value then has the same kind as the receiver
+    pub output: Option<Ty>,
+}
+
+// The signature database
+pub struct Signatures{
+    // Methods keyed by their name, a name can belong to several types
+    methods: HashMap<String,Vec<Signature>>,
+    // Functions without receiver keyed by their type and name joined
+    // together, for example: Stringfrom, see function_key
+    functions: HashMap<String,Signature>,
+}
+
+impl Signatures{
+    fn new() -> Signatures{
+        Signatures{
+            methods: HashMap::new(),
+            functions: HashMap::new(),
         }
-    } else {
-        get_call_rety(func_name)
     }
+
+    // Add the signatures in the given JSON text. A signature replaces the
+    // one already recorded for the same type and name
+    pub fn merge(&mut self, text:&str) -> Result<(),String>{
+        let database = match json::from_str(text){
+            Ok(database) => database,
+            Err(e) => return Err(e.to_string()),
+        };
+        let version = match database.find("version").and_then(|v| v.as_u64()){
+            Some(version) => version as u32,
+            None => return Err("missing \"version\"".to_string()),
+        };
+        if version > SIGNATURE_VERSION{
+            return Err(format!("version {} is newer than the supported version {}",version,SIGNATURE_VERSION))
+        }
+        let entries = match database.find("signatures").and_then(|s| s.as_array()){
+            Some(entries) => entries,
+            None => return Err("missing \"signatures\"".to_string()),
+        };
+        for entry in entries{
+            let signature = parse_signature(entry)?;
+            self.insert(signature);
+        }
+        Ok(())
+    }
+
+    pub fn insert(&mut self, signature:Signature){
+        match signature.receiver{
+            Some(_) =>{
+                let methods = self.methods.entry(signature.name.clone()).or_insert_with(Vec::new);
+                methods.retain(|m| !String::eq(&m.self_ty,&signature.self_ty));
+                methods.insert(0,signature);
+            },
+            None =>{
+                let key = signature.self_ty.clone() + &signature.name;
+                self.functions.insert(key,signature);
+            },
+        }
+    }
+
+    // Find the signature of a method. Several std types can have a method
+    // with the same name, prefer the one of the type of the receiver if it
+    // is known, which for a reference is the type it points to, then the
+    // one whose type has the same kind as the receiver. Otherwise only a
+    // signature that all the types agree on is returned
+    pub fn method(&self, func_name:&str, var_ty:&Ty, type_name:Option<&String>) -> Option<&Signature>{
+        if let Some(candidates) = self.methods.get(func_name){
+            if let Some(type_name) = type_name{
+                if let Some(candidate) = candidates.iter().find(|c| String::eq(&c.self_ty,type_name)){
+                    return Some(candidate)
+                }
+            }
+            for candidate in candidates{
+                let primitive = is_primitive_name(&candidate.self_ty);
+                match var_ty{
+                    &Ty::Primitive if primitive => return Some(candidate),
+                    &Ty::NonPrimitive if !primitive => return Some(candidate),
+                    _=>(),
+                }
+            }
+            if let Some(first) = candidates.first(){
+                if candidates.iter().all(|c| (c.receiver == first.receiver) & (c.params == first.params) & (c.output == first.output)){
+                    return Some(first)
+                }
+            }
+        }
+        None
+    }
+
+    // Find the signature of a function without receiver
+    pub fn function(&self, func_name:&str) -> Option<&Signature>{
+        self.functions.get(func_name)
+    }
+}
+
+// Key of a function without receiver called with the given path: the type
+// or module it belongs to and its name joined together. Only the last two
+// segments are used, so that std::mem::swap is found as mem::swap
+pub fn function_key(path:&[String]) -> String{
+    let start = if path.len() > 2 { path.len()-2 } else { 0 };
+    path[start..].join("")
 }
 
-// Return the return type of functions that have 
-// primitive receiver
-fn get_prim_func_rety (func_name:&str) -> Ty {
-    match func_name {
-        "to_string" | "into_string" | "repeat" | "to_owned"
-        => Ty::NonPrimitive,
-         _=>Ty::Primitive
+// Methods that a shared reference to a String, a Vec or a Box offers but
+// a reference to the type it derefs to does not. `clone` is one of them,
+// since it would clone the reference instead of the owned value
+pub fn owner_only_method(func_name:&str) -> bool{
+    match func_name{
+        "clone" | "capacity" | "as_str" | "as_slice" => true,
+        _ => false,
     }
 }
-// Return the return type of functions that have 
-// ref receiver
-fn get_ref_func_rety(func_name:&str) -> Ty {
-    match func_name {
-        "to_string"| "into_string" | "repeat"|"to_owned"
-        => Ty::NonPrimitive,
-         _=>Ty::Primitive
+
+// The size in bytes of a value of a std type on a 64-bit target, whatever
+// its type parameters are. None if the type is not known or its size
+// depends on its type parameters, for example: Option<T>
+pub fn std_size(name:&str) -> Option<usize>{
+    let size = match name{
+        "bool" | "i8" | "u8" => 1,
+        "i16" | "u16" => 2,
+        "i32" | "u32" | "f32" | "char" => 4,
+        "i64" | "u64" | "f64" | "isize" | "usize" | "Box" | "Rc" | "Arc" => 8,
+        "i128" | "u128" => 16,
+        "String" | "Vec" | "BTreeMap" | "BTreeSet" | "PathBuf" => 24,
+        "VecDeque" => 32,
+        "HashMap" | "HashSet" => 48,
+        _ => return None,
+    };
+    Some(size)
+}
+
+// Whether a std type holds a pointer that is never null, so that an
+// Option of it is no larger than the type itself
+pub fn non_null(name:&str) -> bool{
+    match name{
+        "Box" | "Rc" | "Arc" | "String" | "Vec" => true,
+        _ => false,
     }
 }
-// Return the return type of functions that have 
-// non-primitive receiver
-fn get_non_prim_func_rety(func_name:&str) -> Ty {
-    match func_name{
-        "split_off" => Ty::NonPrimitive,
-        "as_ref"=>Ty::Ref,
-        "is_empty"|"len" =>Ty::Primitive,
-        _=>Ty::NonPrimitive,
-    }
-}
-
-// Return the return type of functions that have 
-// do not have receiver
-fn get_call_rety (func_name:&str) -> Ty{
-        match func_name {
-        "Stringfrom"|"Stringnew"|
-        "Stringwith_capacity"|"Stringfrom_utf16_lossy"|
-        "Stringfrom_raw_parts"|"Stringfrom_utf8_unchecked"
-         => Ty::NonPrimitive,
-         "Stringeq"=>Ty::Primitive,
-        _ => Ty::NonPrimitive,
-         }
+
+// Whether a std type implements Default, so that a value of it can be
+// taken out with std::mem::take
+pub fn has_default(name:&str) -> bool{
+    match name{
+        "String" | "Vec" | "VecDeque" | "HashMap" | "HashSet" | "BTreeMap" | "BTreeSet" |
+        "Option" | "PathBuf" | "Cell" | "RefCell" => true,
+        _ => is_primitive_name(name) & (name != "str"),
+    }
+}
+
+// Names of the std types whose values are copied
+fn is_primitive_name(name:&str) -> bool{
+    match name{
+        "i32" | "bool" | "char"| "f32"| "f64" | "i16" |"i64" |"i8" | "isize" |
+        "u16"| "u32" | "u64" |"u8" |"usize" | "i128"| "u128" | "str" => true,
+        _ => false,
+    }
+}
+
+fn parse_mode(mode:&str) -> Result<Mode,String>{
+    match mode{
+        "value" => Ok(Mode::Value),
+        "ref" => Ok(Mode::Ref),
+        "mut_ref" => Ok(Mode::MutRef),
+        _ => Err(format!("unknown mode \"{}\"",mode)),
+    }
+}
+
+// Get a member of an object that is a string
+pub fn find_str<'j>(json:&'j Json, key:&str) -> Option<&'j str>{
+    json.find(key).and_then(|value| value.as_string())
+}
+
+// Turn an entry of the database into a signature
+fn parse_signature(entry:&Json) -> Result<Signature,String>{
+    let name = match find_str(entry,"name"){
+        Some(name) => name.to_string(),
+        None => return Err("signature without \"name\"".to_string()),
+    };
+    let self_ty = find_str(entry,"type").unwrap_or("").to_string();
+    let receiver = match find_str(entry,"receiver"){
+        Some(mode) => Some(parse_mode(mode)?),
+        None => None,
+    };
+    let mut params = vec![];
+    if let Some(modes) = entry.find("params").and_then(|p| p.as_array()){
+        for mode in modes{
+            match mode.as_string(){
+                Some(mode) => params.push(parse_mode(mode)?),
+                None => return Err(format!("invalid parameter mode of {}",name)),
+            }
+        }
+    }
+    let output = match find_str(entry,"returns"){
+        Some("owned") => Some(Ty::NonPrimitive),
+        Some("copy") => Some(Ty::Primitive),
+        Some("borrowed") => Some(Ty::Ref),
+        Some("generic") => None,
+        _ => return Err(format!("invalid \"returns\" of {}",name)),
+    };
+    Ok(Signature{
+        self_ty: self_ty,
+        name: name,
+        receiver: receiver,
+        params: params,
+        output: output,
+    })
+}
+
+fn mode_name(mode:&Mode) -> &'static str{
+    match mode{
+        &Mode::Value => "value",
+        &Mode::Ref => "ref",
+        &Mode::MutRef => "mut_ref",
+    }
+}
+
+// Turn a signature into an entry of the database
+fn signature_to_json(signature:&Signature) -> Json{
+    let mut entry = BTreeMap::new();
+    entry.insert("type".to_string(),Json::String(signature.self_ty.clone()));
+    entry.insert("name".to_string(),Json::String(signature.name.clone()));
+    if let Some(ref receiver) = signature.receiver{
+        entry.insert("receiver".to_string(),Json::String(mode_name(receiver).to_string()));
+    }
+    let params = signature.params.iter().map(|mode| Json::String(mode_name(mode).to_string())).collect();
+    entry.insert("params".to_string(),Json::Array(params));
+    let output = match signature.output{
+        Some(Ty::NonPrimitive) => "owned",
+        Some(Ty::Primitive) => "copy",
+        Some(Ty::Ref) => "borrowed",
+        None => "generic",
+    };
+    entry.insert("returns".to_string(),Json::String(output.to_string()));
+    Json::Object(entry)
+}
+
+// Write the given signatures as the JSON text of a signature database
+pub fn signatures_to_json(signatures:&[Signature]) -> String{
+    let mut database = BTreeMap::new();
+    database.insert("version".to_string(),Json::U64(SIGNATURE_VERSION as u64));
+    database.insert("signatures".to_string(),Json::Array(signatures.iter().map(signature_to_json).collect()));
+    format!("{}",Json::Object(database).pretty())
+}
+
+// Load the shipped signatures, then the imported signatures from
+// advisor_imported_signatures.json and the user overrides from
+// advisor_signatures.json in the working directory and from the file
+// named by ADVISOR_SIGNATURES, if they exist.
+// Return the file that cannot be read and why if any
+pub fn load_signatures() -> Result<Signatures,String>{
+    let mut signatures = Signatures::new();
+    if let Err(e) = signatures.merge(SHIPPED_SIGNATURES){
+        return Err(format!("Invalid shipped signatures: {}",e))
+    }
+    let mut overrides = vec![IMPORTED_SIGNATURES.to_string(),USER_SIGNATURES.to_string()];
+    if let Ok(path) = env::var("ADVISOR_SIGNATURES"){
+        overrides.push(path);
+    }
+    for path in overrides{
+        if !Path::new(&path).exists(){
+            continue
+        }
+        let mut text = String::new();
+        if let Err(e) = File::open(&path).and_then(|mut file| file.read_to_string(&mut text)){
+            return Err(format!("Cannot read signatures from {}: {}",path,e))
+        }
+        if let Err(e) = signatures.merge(&text){
+            return Err(format!("Invalid signatures in {}: {}",path,e))
+        }
+    }
+    Ok(signatures)
+}
+
+// Entry point of this file
+// Take the function name and receiver, look up the function in
+// the signature database in order to get the return type of the function.
+// Functions that are not in the database return Primitive for a
+// primitive or ref receiver and NonPrimitive otherwise
+pub fn get_func_rety(signatures:&Signatures, func_name : &str, var_ty : Option<Ty>, type_name : Option<&String>) ->Ty{
+    if let Some(var_type) = var_ty{
+        match signatures.method(func_name,&var_type,type_name){
+            Some(&Signature{ output: Some(ref ty), ..}) => ty.clone(),
+            Some(&Signature{ output: None, ..}) => var_type,
+            None => match var_type{
+                Ty::NonPrimitive => Ty::NonPrimitive,
+                _ => Ty::Primitive,
+            },
+        }
+    } else {
+        match signatures.function(func_name){
+            Some(&Signature{ output: Some(ref ty), ..}) => ty.clone(),
+            _ => Ty::NonPrimitive,
+        }
+    }
+}
+
+// Get how a builtin method takes its receiver, if the method is known
+pub fn get_receiver_mode(signatures:&Signatures, func_name : &str, var_ty : &Ty, type_name : Option<&String>) -> Option<Mode>{
+    match signatures.method(func_name,var_ty,type_name){
+        Some(signature) => signature.receiver.clone(),
+        None => None,
+    }
 }
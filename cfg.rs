@@ -0,0 +1,303 @@
+ // File: The control flow graph of the tool
+ // Purpose: Functions defined in this file are used to lower the body of
+ //                 each function into basic blocks connected by control flow
+ //                 edges while the symbol table is built, so that branches,
+ //                 loops and early exits can be followed by the analyzer
+ // Author : Ziling Zhou (802414)
+
+// The entry and exit block of every control flow graph
+pub const ENTRY: usize = 0;
+pub const EXIT: usize = 1;
+
+// A variable or a field of it, together with the line on which its value
+// was declared or assigned. Shadowed variables and reassigned values are
+// therefore different places
+#[derive(Debug,Clone,PartialEq,Eq,Hash)]
+pub struct Place{
+    pub var: Vec<String>,
+    pub decl: usize,
+}
+
+// What happens to a place at a program point
+#[derive(Debug,Clone,PartialEq)]
+pub enum Action{
+    // The place gets a new value
+    Def,
+    // The value is read or borrowed
+    Use,
+    // The ownership of the value is moved out of the place
+    Move,
+}
+
+// A program point, the points of a block are executed in order
+#[derive(Debug,Clone)]
+pub struct Point{
+    pub line: usize,
+    pub action: Action,
+    pub place: Place,
+}
+
+#[derive(Debug,Clone)]
+pub struct BasicBlock{
+    pub points: Vec<Point>,
+    pub succs: Vec<usize>,
+    pub preds: Vec<usize>,
+    // Line before which a statement at the start of the block can be
+    // added, 0 if there is no such line
+    pub line: usize,
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum RegionKind{
+    // if, if let and match
+    Branch,
+    // loop, while, while let and for
+    Loop,
+}
+
+// A branching or looping expression
+#[derive(Debug,Clone)]
+pub struct Region{
+    pub kind: RegionKind,
+    // Lines covered by the expression
+    pub start: usize,
+    pub end: usize,
+    // For a loop, the block evaluated at the start of each iteration.
+    // For a branch, the block the branch starts from
+    pub head: usize,
+    // The block control flow continues in after the expression
+    pub exit: usize,
+    pub label: Option<String>,
+    // Blocks inside the expression
+    pub blocks: Vec<usize>,
+}
+
+// The control flow graph of a function body
+#[derive(Debug)]
+pub struct Cfg{
+    pub name: String,
+    pub blocks: Vec<BasicBlock>,
+    pub regions: Vec<Region>,
+}
+
+impl Cfg{
+    // Print the blocks and the program points of the graph
+    pub fn debug(&self){
+        println!("Control flow graph of {}:", self.name);
+        let mut index = 0;
+        for block in &self.blocks{
+            let name = match index{
+                ENTRY => " (entry)",
+                EXIT => " (exit)",
+                _ => "",
+            };
+            println!("  bb{}{} (line {}): preds {:?}, succs {:?}", index, name, block.line, block.preds, block.succs);
+            for point in &block.points{
+                println!("      line {}: {:?} {} (declared on line {})",
+                             point.line, point.action, point.place.var.join("."), point.place.decl);
+            }
+            index+=1;
+        }
+        for region in &self.regions{
+            println!("  {:?} on line {}-{}: head bb{}, exit bb{}, blocks {:?}",
+                         region.kind, region.start, region.end, region.head, region.exit, region.blocks);
+        }
+    }
+}
+
+// Build a control flow graph while the body of a function is visited.
+// Program points are added to the current block; the visitor opens a
+// region for each branching or looping expression
+pub struct CfgBuilder{
+    cfg: Cfg,
+    current: usize,
+    // Regions that are being visited, the innermost one is the last
+    open: Vec<Region>,
+}
+
+impl CfgBuilder{
+    pub fn new(name:String) -> CfgBuilder{
+        let mut builder = CfgBuilder{
+            cfg: Cfg{ name: name, blocks: vec![], regions: vec![] },
+            current: ENTRY,
+            open: vec![],
+        };
+        builder.new_block(0);
+        builder.new_block(0);
+        builder
+    }
+
+    pub fn new_block(&mut self, line:usize) -> usize{
+        self.cfg.blocks.push(BasicBlock{ points: vec![], succs: vec![], preds: vec![], line: line });
+        self.cfg.blocks.len()-1
+    }
+
+    pub fn add_edge(&mut self, from:usize, to:usize){
+        if !self.cfg.blocks[from].succs.contains(&to){
+            self.cfg.blocks[from].succs.push(to);
+        }
+    }
+
+    // Add a program point to the current block
+    pub fn record(&mut self, line:usize, action:Action, place:Place){
+        let current = self.current;
+        self.cfg.blocks[current].points.push(Point{ line: line, action: action, place: place });
+    }
+
+    // The current block and the number of points in it
+    pub fn position(&self) -> (usize,usize){
+        (self.current,self.cfg.blocks[self.current].points.len())
+    }
+
+    // Start a branching expression, the arms start from the current block
+    pub fn begin_branch(&mut self, start:usize, end:usize){
+        let join = self.new_block(end+1);
+        let head = self.current;
+        self.open.push(Region{
+            kind: RegionKind::Branch,
+            start: start,
+            end: end,
+            head: head,
+            exit: join,
+            label: None,
+            blocks: vec![join+1],
+        });
+    }
+
+    // Start an arm of the innermost branch, whose first line inside is
+    // given (0 if the arm is on one line)
+    pub fn branch_arm(&mut self, line:usize){
+        let head = match self.open.last(){
+            Some(region) => region.head,
+            None => return,
+        };
+        let arm = self.new_block(line);
+        self.add_edge(head,arm);
+        self.current = arm;
+    }
+
+    // End an arm of the innermost branch
+    pub fn end_arm(&mut self){
+        let join = match self.open.last(){
+            Some(region) => region.exit,
+            None => return,
+        };
+        let current = self.current;
+        self.add_edge(current,join);
+    }
+
+    // End the innermost branch. Without an else arm, control flow can
+    // skip all the arms
+    pub fn end_branch(&mut self, exhaustive:bool){
+        if let Some(region) = self.close_region(){
+            if !exhaustive{
+                self.add_edge(region.head,region.exit);
+            }
+            self.current = region.exit;
+        }
+    }
+
+    // Start a loop, the current block becomes the head of the loop.
+    // The condition of a while loop is visited in the head
+    pub fn begin_loop(&mut self, start:usize, end:usize, label:Option<String>){
+        let exit = self.new_block(end+1);
+        let head = self.new_block(0);
+        let current = self.current;
+        self.add_edge(current,head);
+        self.current = head;
+        self.open.push(Region{
+            kind: RegionKind::Loop,
+            start: start,
+            end: end,
+            head: head,
+            exit: exit,
+            label: label,
+            blocks: vec![head],
+        });
+    }
+
+    // Start the body of a while or for loop, which may not be entered
+    pub fn loop_body(&mut self, line:usize){
+        let (head,exit) = match self.open.last(){
+            Some(region) => (region.head,region.exit),
+            None => return,
+        };
+        let body = self.new_block(line);
+        self.add_edge(head,body);
+        self.add_edge(head,exit);
+        self.current = body;
+    }
+
+    // End the innermost loop, the end of the body goes back to the head
+    pub fn end_loop(&mut self){
+        if let Some(region) = self.close_region(){
+            let current = self.current;
+            self.add_edge(current,region.head);
+            self.current = region.exit;
+        }
+    }
+
+    fn close_region(&mut self) -> Option<Region>{
+        match self.open.pop(){
+            Some(mut region) => {
+                let first = region.blocks[0];
+                region.blocks = (first..self.cfg.blocks.len()).collect();
+                self.cfg.regions.push(region.clone());
+                Some(region)
+            },
+            None => None,
+        }
+    }
+
+    // Leave the loop with the given label, or the innermost loop,
+    // by break or continue. Code after the jump is unreachable
+    pub fn jump_loop(&mut self, label:Option<String>, is_continue:bool){
+        let mut target = None;
+        for region in self.open.iter().rev(){
+            if region.kind != RegionKind::Loop{
+                continue
+            }
+            if label.is_none() | (region.label == label){
+                target = Some(if is_continue { region.head } else { region.exit });
+                break;
+            }
+        }
+        if let Some(target) = target{
+            let current = self.current;
+            self.add_edge(current,target);
+            self.current = self.new_block(0);
+        }
+    }
+
+    // Return from the function, code after the return is unreachable
+    pub fn jump_exit(&mut self){
+        let current = self.current;
+        self.add_edge(current,EXIT);
+        self.current = self.new_block(0);
+    }
+
+    // The `?` operator may return from the function or continue
+    pub fn may_exit(&mut self){
+        let current = self.current;
+        self.add_edge(current,EXIT);
+        let next = self.new_block(0);
+        self.add_edge(current,next);
+        self.current = next;
+    }
+
+    // Finish the graph, the end of the body returns from the function
+    pub fn finish(mut self) -> Cfg{
+        let current = self.current;
+        self.add_edge(current,EXIT);
+        let mut preds = vec![vec![]; self.cfg.blocks.len()];
+        for (from,block) in self.cfg.blocks.iter().enumerate(){
+            for to in &block.succs{
+                preds[*to].push(from);
+            }
+        }
+        for (block,pred) in self.cfg.blocks.iter_mut().zip(preds){
+            block.preds = pred;
+        }
+        self.cfg
+    }
+}
@@ -0,0 +1,325 @@
+ // File: The signature importer of the tool
+ // Purpose: Functions defined in this file are mainly used for reading
+ //                 rustdoc JSON files (generated by
+ //                 `cargo rustdoc -- --output-format json`) and turning the
+ //                 methods and functions found in them into the signature
+ //                 database used by the builtin module
+ // Author : Ziling Zhou (802414)
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Read,Write};
+
+use builtin::{self,Ty,Mode,Signature,find_str};
+use serialize::json::{self,Json};
+
+
+pub const USAGE: &'static str = "Usage: advisor import-signatures [-o <output>] <rustdoc json>...";
+
+// Entry point of this file.
+// Return the usage or the input or output that failed and why if the
+// signatures cannot be imported
+pub fn import_signatures(args:&[String]) -> Result<(),String>{
+    let mut output = builtin::IMPORTED_SIGNATURES.to_string();
+    let mut inputs = vec![];
+    let mut index = 0;
+    while index < args.len(){
+        if String::eq(&args[index],"-o"){
+            if index+1 == args.len(){
+                return Err(format!("-o needs an output file\n{}",USAGE))
+            }
+            output = args[index+1].clone();
+            index+=1;
+        }else{
+            inputs.push(args[index].clone());
+        }
+        index+=1;
+    }
+    if inputs.is_empty(){
+        return Err(USAGE.to_string())
+    }
+
+    let mut signatures = vec![];
+    for input in &inputs{
+        let mut text = String::new();
+        if let Err(e) = File::open(input).and_then(|mut file| file.read_to_string(&mut text)){
+            return Err(format!("Cannot read {}: {}",input,e))
+        }
+        let doc = match json::from_str(&text){
+            Ok(doc) => doc,
+            Err(e) => return Err(format!("{} is not valid JSON: {}",input,e)),
+        };
+        match import_crate(&doc){
+            Ok(mut found) => {
+                println!("{}: {} signatures",input,found.len());
+                signatures.append(&mut found);
+            },
+            Err(e) => return Err(format!("Cannot import {}: {}",input,e)),
+        }
+    }
+
+    let written = File::create(&output).and_then(|mut file| file.write_all(builtin::signatures_to_json(&signatures).as_bytes()));
+    if let Err(e) = written{
+        return Err(format!("Cannot write {}: {}",output,e))
+    }
+    println!("{} signatures written to {}",signatures.len(),output);
+    Ok(())
+}
+
+// Collect the signatures of all the methods in impl blocks and all the
+// free functions of a crate
+fn import_crate(doc:&Json) -> Result<Vec<Signature>,String>{
+    if doc.find("format_version").is_none(){
+        return Err("not a rustdoc JSON file".to_string())
+    }
+    let index = match doc.find("index").and_then(|i| i.as_object()){
+        Some(index) => index,
+        None => return Err("missing \"index\"".to_string()),
+    };
+    let mut signatures = vec![];
+    let mut methods = HashSet::new();
+
+    // Methods of impl blocks, the type they belong to is the `for` type
+    for item in index.values(){
+        let impl_block = match item_kind(item){
+            Some(("impl",inner)) => inner,
+            _ => continue,
+        };
+        let self_ty = match impl_block.find("for"){
+            Some(ty) => ty,
+            None => continue,
+        };
+        let items = match impl_block.find("items").and_then(|i| i.as_array()){
+            Some(items) => items,
+            None => continue,
+        };
+        for id in items{
+            let id = id_key(id);
+            if let Some(method) = index.get(&id){
+                if let Some(signature) = function_signature(&type_name(self_ty),self_ty,method){
+                    signatures.push(signature);
+                }
+            }
+            methods.insert(id);
+        }
+    }
+
+    // Free functions are named after the module that contains them,
+    // for example: mem::swap is recorded as type "mem" and name "swap",
+    // which is how builtin::function_key finds mem::swap and std::mem::swap
+    let paths = doc.find("paths").and_then(|p| p.as_object());
+    for (id,item) in index{
+        if methods.contains(id){
+            continue
+        }
+        match item_kind(item){
+            Some(("function",_)) => (),
+            _ => continue,
+        }
+        let mut module = "".to_string();
+        if let Some(summary) = paths.and_then(|p| p.get(id)){
+            if let Some(path) = summary.find("path").and_then(|p| p.as_array()){
+                if path.len() >= 2{
+                    module = path[path.len()-2].as_string().unwrap_or("").to_string();
+                }
+            }
+        }
+        if let Some(signature) = function_signature(&module,&Json::Null,item){
+            signatures.push(signature);
+        }
+    }
+    Ok(signatures)
+}
+
+// Ids are strings in older rustdoc versions and numbers in newer ones
+fn id_key(id:&Json) -> String{
+    match id{
+        &Json::String(ref s) => s.clone(),
+        &Json::U64(n) => n.to_string(),
+        &Json::I64(n) => n.to_string(),
+        _ => "".to_string(),
+    }
+}
+
+// Older rustdoc versions describe an item or a type as
+// {"kind": k, "inner": v}, newer ones as {k: v}
+fn item_kind(item:&Json) -> Option<(&str,&Json)>{
+    if let (Some(kind),Some(inner)) = (find_str(item,"kind"),item.find("inner")){
+        return Some((kind,inner))
+    }
+    let inner = match item.find("inner"){
+        Some(inner) => inner,
+        None => return None,
+    };
+    type_kind(inner)
+}
+
+fn type_kind(ty:&Json) -> Option<(&str,&Json)>{
+    match ty{
+        &Json::String(ref kind) => Some((kind.as_str(),ty)),
+        &Json::Object(ref members) => {
+            if let (Some(kind),Some(inner)) = (find_str(ty,"kind"),ty.find("inner")){
+                return Some((kind,inner))
+            }
+            if members.len() == 1{
+                members.iter().next().map(|(kind,inner)| (kind.as_str(),inner))
+            }else{
+                None
+            }
+        },
+        _ => None,
+    }
+}
+
+// Name of a type as it is written in the signature database
+fn type_name(ty:&Json) -> String{
+    match type_kind(ty){
+        Some(("resolved_path",path)) => {
+            let name = find_str(path,"name").or_else(|| find_str(path,"path")).unwrap_or("");
+            name.rsplit("::").next().unwrap_or("").to_string()
+        },
+        Some(("primitive",name)) | Some(("generic",name)) => name.as_string().unwrap_or("").to_string(),
+        Some(("slice",_)) => "slice".to_string(),
+        Some(("array",_)) => "array".to_string(),
+        Some(("borrowed_ref",inner)) => match inner.find("type"){
+            Some(t) => type_name(t),
+            None => "".to_string(),
+        },
+        _ => "".to_string(),
+    }
+}
+
+// How a parameter of the given type is passed
+fn param_mode(ty:&Json) -> Mode{
+    match type_kind(ty){
+        Some(("borrowed_ref",inner)) => {
+            let mutable = inner.find("is_mutable").or_else(|| inner.find("mutable"))
+                                    .and_then(|m| m.as_boolean()).unwrap_or(false);
+            if mutable { Mode::MutRef } else { Mode::Ref }
+        },
+        _ => Mode::Value,
+    }
+}
+
+// Build the signature of a function item. `self_ty` is the type of the
+// impl block, Null for free functions
+fn function_signature(type_name:&str, self_ty:&Json, item:&Json) -> Option<Signature>{
+    let function = match item_kind(item){
+        Some(("function",inner)) | Some(("method",inner)) => inner,
+        _ => return None,
+    };
+    let name = match find_str(item,"name"){
+        Some(name) => name.to_string(),
+        None => return None,
+    };
+    // The declaration is called "sig" in newer rustdoc versions
+    let decl = match function.find("sig").or_else(|| function.find("decl")){
+        Some(decl) => decl,
+        None => return None,
+    };
+    let inputs = match decl.find("inputs").and_then(|i| i.as_array()){
+        Some(inputs) => inputs,
+        None => return None,
+    };
+
+    // Each input is a [name, type] pair, a method's receiver is named self
+    let mut receiver = None;
+    let mut params = vec![];
+    let mut first = true;
+    for input in inputs{
+        let pair = match input.as_array(){
+            Some(pair) if pair.len() == 2 => pair,
+            _ => return None,
+        };
+        let mode = param_mode(&pair[1]);
+        if first & (pair[0].as_string() == Some("self")){
+            receiver = Some(mode);
+        }else{
+            params.push(mode);
+        }
+        first = false;
+    }
+
+    let output = match decl.find("output"){
+        None | Some(&Json::Null) => Some(Ty::Primitive),
+        Some(ty) => output_kind(ty,self_ty,&receiver),
+    };
+    Some(Signature{
+        self_ty: type_name.to_string(),
+        name: name,
+        receiver: receiver,
+        params: params,
+        output: output,
+    })
+}
+
+// Names of std types that are not primitive but are Copy
+fn is_copy_path(name:&str) -> bool{
+    match name{
+        "Ordering" | "Duration" | "Instant" | "TypeId" | "Layout" | "NonZeroUsize" |
+        "NonZeroU32" | "NonZeroU64" | "Wrapping" | "PhantomData" => true,
+        _ => false,
+    }
+}
+
+// Classify the return type of a function. None means the return type
+// depends on a generic parameter
+fn output_kind(ty:&Json, self_ty:&Json, receiver:&Option<Mode>) -> Option<Ty>{
+    match type_kind(ty){
+        Some(("primitive",_)) => Some(Ty::Primitive),
+        Some(("borrowed_ref",_)) | Some(("raw_pointer",_)) => Some(Ty::Ref),
+        // Self is the type of the impl block
+        Some(("generic",name)) => {
+            match (name.as_string(),self_ty){
+                (Some("Self"),_) if !self_ty.is_null() => output_kind(self_ty,&Json::Null,receiver),
+                _ => None,
+            }
+        },
+        Some(("tuple",elements)) => {
+            let mut kind = Some(Ty::Primitive);
+            for element in elements.as_array().unwrap_or(&vec![]){
+                match output_kind(element,self_ty,receiver){
+                    Some(Ty::Primitive) => (),
+                    None => return None,
+                    _ => kind = Some(Ty::NonPrimitive),
+                }
+            }
+            kind
+        },
+        Some(("resolved_path",path)) => {
+            let name = type_name(ty);
+            let mut generic = false;
+            let args = path.find("args").and_then(|a| a.find("angle_bracketed"))
+                                .and_then(|a| a.find("args")).and_then(|a| a.as_array());
+            for arg in args.unwrap_or(&vec![]){
+                // A lifetime or reference argument means the value borrows,
+                // for example: Option<&T> or Iter<'a, T>
+                if arg.find("lifetime").is_some(){
+                    return Some(Ty::Ref)
+                }
+                if let Some(arg_ty) = arg.find("type"){
+                    match type_kind(arg_ty){
+                        Some(("borrowed_ref",_)) => return Some(Ty::Ref),
+                        Some(("generic",param)) if param.as_string() != Some("Self") => generic = true,
+                        _ => (),
+                    }
+                }
+            }
+            // Only wrappers take the kind of their parameter, a Vec<T> is
+            // owned whatever T is
+            if generic & ((name == "Option") | (name == "Result")){
+                None
+            }else if is_copy_path(&name){
+                Some(Ty::Primitive)
+            }else{
+                Some(Ty::NonPrimitive)
+            }
+        },
+        // An anonymous iterator usually borrows the receiver
+        Some(("impl_trait",_)) => match receiver{
+            &Some(Mode::Ref) | &Some(Mode::MutRef) => Some(Ty::Ref),
+            _ => Some(Ty::NonPrimitive),
+        },
+        _ => Some(Ty::NonPrimitive),
+    }
+}
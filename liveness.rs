@@ -0,0 +1,202 @@
+ // File: The liveness analysis of the tool
+ // Purpose: Functions defined in this file compute, over the control flow
+ //                 graph of a function, which places are still needed at each
+ //                 program point, and from that the points after which the
+ //                 value of a place can be dropped
+ // Author : Ziling Zhou (802414)
+
+use std::collections::{HashMap,HashSet};
+
+use cfg::{Cfg,Action,Place,Point,Region};
+
+// The places that are live at the end of each block
+pub struct Liveness{
+    live_out: Vec<HashSet<Place>>,
+    // All the places used in the function
+    places: Vec<Place>,
+}
+
+// Whether one of the places is a part of the other, for example:
+// p and p.x. A use of p.x needs p and a use of p needs p.x
+fn related(a:&Place, b:&Place) -> bool{
+    if a.decl != b.decl{
+        return false
+    }
+    let len = if a.var.len() < b.var.len() { a.var.len() } else { b.var.len() };
+    a.var[..len] == b.var[..len]
+}
+
+// Whether the place is `part` or a part of it
+fn contains(whole:&Place, part:&Place) -> bool{
+    (whole.decl == part.decl) && (whole.var.len() <= part.var.len())
+        && (whole.var[..] == part.var[..whole.var.len()])
+}
+
+impl Liveness{
+    // The places that are live before a point, given the ones that are
+    // live after it
+    fn transfer(&self, point:&Point, live:&mut HashSet<Place>){
+        match point.action{
+            // A new value, the old one is not needed before the point
+            Action::Def => live.retain(|place| !contains(&point.place,place)),
+            Action::Use | Action::Move => {
+                for place in &self.places{
+                    if related(&point.place,place){
+                        live.insert(place.clone());
+                    }
+                }
+            },
+        }
+    }
+
+    // The places that are live at the start of a block
+    fn live_in(&self, cfg:&Cfg, block:usize) -> HashSet<Place>{
+        let mut live = self.live_out[block].clone();
+        for point in cfg.blocks[block].points.iter().rev(){
+            self.transfer(point,&mut live);
+        }
+        live
+    }
+
+    // Whether a place is live before the point with the given index
+    // of a block
+    pub fn live_at(&self, cfg:&Cfg, block:usize, index:usize, place:&Place) -> bool{
+        let mut live = self.live_out[block].clone();
+        for point in cfg.blocks[block].points[index..].iter().rev(){
+            self.transfer(point,&mut live);
+        }
+        live.contains(place)
+    }
+
+    // Find the lines before which the value of each place can be dropped,
+    // one for each control flow path on which the value dies.
+    // A value dies after its last use on a path, or on an edge whose
+    // target does not need it. A value that dies on only some of the
+    // edges into a join is dropped after the branch or loop instead
+    pub fn drop_points(&self, cfg:&Cfg) -> HashMap<Place,Vec<usize>>{
+        // Dead points as (block, line before which to drop)
+        let mut deaths:HashMap<Place,Vec<(usize,usize)>> = HashMap::new();
+        // Values that die on only some of the edges into a block
+        let mut partial = vec![];
+        let mut live_in = vec![];
+        for block in 0..cfg.blocks.len(){
+            live_in.push(self.live_in(cfg,block));
+        }
+
+        for (index,block) in cfg.blocks.iter().enumerate(){
+            // The last use of a value on a path
+            let mut live = self.live_out[index].clone();
+            for point in block.points.iter().rev(){
+                if point.action == Action::Use{
+                    for place in &self.places{
+                        if related(&point.place,place) & !live.contains(place){
+                            deaths.entry(place.clone()).or_insert_with(Vec::new).push((index,point.line+1));
+                        }
+                    }
+                }
+                self.transfer(point,&mut live);
+            }
+            // A value needed at the end of the block but not by a successor
+            for succ in &block.succs{
+                for place in &self.live_out[index]{
+                    if !live_in[*succ].contains(place){
+                        let target = &cfg.blocks[*succ];
+                        let dies_on_all = target.preds.iter().all(|pred| self.live_out[*pred].contains(place));
+                        if dies_on_all & (target.line != 0){
+                            deaths.entry(place.clone()).or_insert_with(Vec::new).push((*succ,target.line));
+                        }else{
+                            partial.push((place.clone(),*succ));
+                        }
+                    }
+                }
+            }
+        }
+        for (place,target) in partial{
+            self.hoist_death(cfg,&place,target,&mut deaths);
+        }
+
+        let mut points = HashMap::new();
+        for (place,dead) in deaths{
+            let mut lines:Vec<usize> = dead.into_iter().map(|(_,line)| line).filter(|line| *line != 0).collect();
+            lines.sort();
+            lines.dedup();
+            points.insert(place,lines);
+        }
+        points
+    }
+
+    // A value that dies on only some of the edges into a block, or on an
+    // edge into a block without a line, is dropped after the innermost
+    // branch or loop the edge leaves instead, unless the value may be
+    // moved inside of it
+    fn hoist_death(&self,
+                        cfg:&Cfg,
+                        place:&Place,
+                        target:usize,
+                        deaths:&mut HashMap<Place,Vec<(usize,usize)>>)
+    {
+        if let Some(region) = innermost_region(cfg,target){
+            let moved = region.blocks.iter().any(|b| cfg.blocks[*b].points.iter().any(|point|
+                            (point.action == Action::Move) & related(&point.place,place)));
+            if moved | (cfg.blocks[region.exit].line == 0){
+                return
+            }
+            let dead = deaths.entry(place.clone()).or_insert_with(Vec::new);
+            dead.retain(|&(b,_)| !region.blocks.contains(&b));
+            dead.push((region.exit,cfg.blocks[region.exit].line));
+        }
+    }
+}
+
+// The innermost branch or loop that ends in the given block or that
+// contains it
+fn innermost_region(cfg:&Cfg, block:usize) -> Option<&Region>{
+    let mut found:Option<&Region> = None;
+    for region in &cfg.regions{
+        if (region.exit == block) | region.blocks.contains(&block){
+            let smaller = match found{
+                Some(other) => region.blocks.len() < other.blocks.len(),
+                None => true,
+            };
+            if smaller{
+                found = Some(region);
+            }
+        }
+    }
+    found
+}
+
+// Entry point of this file
+// Compute the places that are live at the end of each block of the graph
+pub fn analyze(cfg:&Cfg) -> Liveness{
+    let mut places = vec![];
+    for block in &cfg.blocks{
+        for point in &block.points{
+            if !places.contains(&point.place){
+                places.push(point.place.clone());
+            }
+        }
+    }
+    let mut liveness = Liveness{
+        live_out: vec![HashSet::new(); cfg.blocks.len()],
+        places: places,
+    };
+
+    // Iterate until nothing changes, a block needs what its
+    // successors need at their start
+    let mut changed = true;
+    while changed{
+        changed = false;
+        for block in (0..cfg.blocks.len()).rev(){
+            let mut live = HashSet::new();
+            for succ in &cfg.blocks[block].succs{
+                live.extend(liveness.live_in(cfg,*succ));
+            }
+            if live != liveness.live_out[block]{
+                liveness.live_out[block] = live;
+                changed = true;
+            }
+        }
+    }
+    liveness
+}
@@ -7,41 +7,61 @@
 
  #![feature(box_syntax)]
  #![feature(rustc_private)]
+ #![feature(retain_hash_collection)]
+// The advisor matches on references to AST nodes with `&Node(ref x)`
+// patterns and passes variable paths around as `&Vec<String>`
+// throughout, keep clippy from asking to rewrite these idioms
+ #![cfg_attr(feature="cargo-clippy", allow(match_ref_pats,ptr_arg,single_match,
+                                           cyclomatic_complexity,too_many_arguments,
+                                           explicit_counter_loop))]
 extern crate syntax;
+extern crate serialize;
 
 mod pre_record;
 mod builtin;
 mod analyzer;
 mod resolve;
+mod import;
+mod cfg;
+mod liveness;
+mod moves;
+mod summary;
 
-use std::collections::{HashMap};
+use std::collections::{HashMap,HashSet};
 use std::env;
-use std::path::Path;
+use std::fs::File;
+use std::io::{self,Read,Write};
+use std::process;
 use std::mem;
 use std::clone::Clone;
 use std::ops::DerefMut;
 
 use syntax::ast;
-use syntax::ast::{Stmt,StmtKind,PatKind,NodeId,Block,ExprKind,Mac,Expr};
+use syntax::ast::{Stmt,StmtKind,PatKind,NodeId,Block,ExprKind,Mac,Expr,Pat,Arm,FnDecl,FunctionRetTy};
 use syntax::codemap::{CodeMap, Span};
 use syntax::errors::DiagnosticBuilder;
 use syntax::parse::{self, ParseSess};
-use syntax::parse::token::Token;
-use syntax::visit::{self,Visitor};
+use syntax::parse::token::{self,Token,DelimToken};
+use syntax::print::pprust;
+use syntax::visit::{self,Visitor,FnKind};
 use syntax::tokenstream::{TokenTree};
+use syntax::ptr::P;
 
-use builtin::Ty;
-use pre_record::FnInfo;
+use builtin::{Ty,Mode,Signatures};
+use pre_record::{FnInfo,Alias};
 use resolve::Resolver;
+use cfg::{Action,Place,Cfg,CfgBuilder};
+use liveness::Liveness;
+use moves::{MoveState,MoveAnalysis};
+use summary::{SummaryBuilder,Effect};
 
 
 // This function is used to build a AST tree for the input program
-fn parse<'a, T: ?Sized + AsRef<Path>>(path: &T,
-                                      parse_session: &'a ParseSess)
-                                      -> Result<ast::Crate, Option<DiagnosticBuilder<'a>>> {
-    let path = path.as_ref();
-
-    match parse::parse_crate_from_file(path, parse_session) {
+fn parse<'a>(file_name: &str,
+                    source: &str,
+                    parse_session: &'a ParseSess)
+                    -> Result<ast::Crate, Option<DiagnosticBuilder<'a>>> {
+    match parse::parse_crate_from_source_str(file_name.to_string(), source.to_string(), parse_session) {
         // There may be   parse errors that the parser recovered from, which
         // would be treat as error here
         Ok(_) if parse_session.span_diagnostic.has_errors() => Err(None),
@@ -50,23 +70,81 @@ fn parse<'a, T: ?Sized + AsRef<Path>>(path: &T,
     }
 }
 
+const USAGE: &'static str = "Usage: advisor [--debug-cfg] <file_name>.rs";
+
+// Print an error or the usage on stderr
+fn report(message: &str){
+    let _ = writeln!(io::stderr(),"{}",message);
+}
+
 // Entry piont of this program.
 fn main() {
     // Take filename from command line
     let args: Vec<String> = env::args().collect();
+    if args.len() < 2{
+        report(&format!("{}\n       {}",USAGE,import::USAGE));
+        process::exit(1);
+    }
+    // Build the signature database from rustdoc JSON files instead of
+    // analyzing a program
+    if String::eq(&args[1],"import-signatures"){
+        if let Err(e) = import::import_signatures(&args[2..]){
+            report(&e);
+            process::exit(1);
+        }
+        return
+    }
+    // The control flow graphs are only printed when they are asked for
+    let debug_cfg = String::eq(&args[1],"--debug-cfg");
+    let file_name = match args.get(if debug_cfg { 2 } else { 1 }){
+        Some(file_name) => file_name.clone(),
+        None => {
+            report(USAGE);
+            process::exit(1);
+        },
+    };
+    // The program is read here, the parser would panic on a file that
+    // cannot be read
+    let mut source = String::new();
+    if let Err(e) = File::open(&file_name).and_then(|mut file| file.read_to_string(&mut source)){
+        report(&format!("Cannot read {}: {}",file_name,e));
+        process::exit(1);
+    }
     // Build a new parse session for parser
     let parse_session = ParseSess::new();
-    // unwrap ast from result
-    let krate = parse(args[1].as_str(), &parse_session).unwrap();
+    let krate = match parse(&file_name, &source, &parse_session){
+        Ok(krate) => krate,
+        Err(diagnostic) => {
+            // Errors the parser recovered from are already printed
+            if let Some(mut diagnostic) = diagnostic{
+                diagnostic.emit();
+            }
+            report(&format!("Cannot parse {}",file_name));
+            process::exit(1);
+        },
+    };
     // Get function and enum records from input program
     let pre_records =  pre_record::get_records(&krate);
     let fun_records = pre_records.fun_record;
     let enum_list = pre_records.enum_record;
     let structure_list = pre_records.struct_record;
+    let field_types = pre_records.field_type_record;
+    let field_forms = pre_records.field_form_record;
+    let default_types = pre_records.default_record;
+    let alias_list = pre_records.alias_record;
+    let type_keys = pre_records.type_keys;
+    // Load the signatures of builtin functions
+    let signatures = match builtin::load_signatures(){
+        Ok(signatures) => signatures,
+        Err(e) => {
+            report(&e);
+            process::exit(1);
+        },
+    };
 
     // Start to analyze the whole program
     analyze_prog(&krate,parse_session.codemap(), 
-                            &fun_records,&enum_list,&structure_list,args[1].clone());
+                            &fun_records,&enum_list,&structure_list,&field_types,&field_forms,&default_types,&type_keys,&alias_list,&signatures,&source,debug_cfg);
 
 }
 
@@ -77,9 +155,16 @@ fn main() {
 fn analyze_prog(krate:&ast::Crate,
                             codemap: &CodeMap, 
                             fun_records :& HashMap<String, FnInfo>,
-                            enum_list: & HashMap<String,Vec<String>>, 
+                            enum_list: & HashMap<String,HashMap<String,HashMap<String,Ty>>>, 
                             structure_list:&HashMap<String,HashMap<String,Ty>>,
-                            file_name:String
+                            field_types:&HashMap<String,HashMap<String,String>>,
+                            field_forms:&HashMap<String,HashMap<String,String>>,
+                            default_types:&HashSet<String>,
+                            type_keys:&HashSet<String>,
+                            alias_list:&HashMap<String,Alias>,
+                            signatures:&Signatures,
+                            source:&str,
+                            debug_cfg:bool
                             ) 
 {
     // Build a new SymbolTable
@@ -92,8 +177,34 @@ fn analyze_prog(krate:&ast::Crate,
         scope_end : codemap.lookup_char_pos(krate.module.inner.hi).line,
         fun_records:  fun_records,
         call_records: HashMap::new(),
+        clone_records: vec![],
+        conversion_records: vec![],
+        borrowed_moves: vec![],
+        mutated_places: vec![],
+        lent_places: vec![],
+        mut_bindings: vec![],
+        mut_borrows: vec![],
+        returns: vec![],
+        result_uses: vec![],
+        field_inits: vec![],
+        field_writes: vec![],
         enum_list: enum_list,
         structure_list:structure_list,
+        field_types:field_types,
+        field_forms:field_forms,
+        default_types:default_types,
+        type_keys:type_keys,
+        alias_list:alias_list,
+        signatures:signatures,
+        cfg_builder:None,
+        cfgs:vec![],
+        move_analyses:vec![],
+        summary_builder:None,
+        summaries:vec![],
+        params:vec![],
+        mod_path:vec![],
+        self_type:None,
+        fn_prefix:None,
     };
 
     let node_id = NodeId::new(0);
@@ -103,24 +214,22 @@ fn analyze_prog(krate:&ast::Crate,
 
     // Print out all the variable information in the symbol table
     println!("\nVariable Information:\n");
-    visitor.debug();
+    visitor.debug(debug_cfg);
 
     //  Call start_analyze to analyze the symbol table
     //  Print out the analyze result (advice)
     println!("\nStart analyze...\n");
-    visitor.start_analyze(file_name);
+    visitor.start_analyze(source);
 }
 
 // Override drop for SymbolTable in order to avoid segfault
 impl<'a> Drop for SymbolTable<'a>{
     fn drop(&mut self){
         mem::replace(&mut self.enclose_scope, vec![]);
-        let mut outer_scope = mem::replace(&mut self.outer_scope, None);
-        loop{
-            outer_scope = match outer_scope{
-                Some(mut n )=>mem::replace(&mut n.outer_scope, None),
-                None => break,
-            }
+        // The outer scope is not owned by this scope, the box only aliases
+        // the scope that encloses this one, so it must not be freed here
+        if let Some(outer_scope) = mem::replace(&mut self.outer_scope, None){
+            mem::forget(outer_scope);
         }
     }
 }
@@ -135,8 +244,58 @@ pub struct SymbolTable<'a>{
     scope_end: usize,
     fun_records: & 'a HashMap<String, FnInfo>,
     call_records: HashMap<String,Vec<CallInfo>>,
-    enum_list:&'a HashMap<String,Vec<String>>,
-    structure_list:&'a HashMap<String,HashMap<String,Ty>>
+    // Values cloned from the variables of this scope
+    clone_records: Vec<CloneInfo>,
+    // Owned strings created in this scope that are only borrowed
+    conversion_records: Vec<ConversionInfo>,
+    // Fields reached through a mutable reference that are cloned
+    borrowed_moves: Vec<BorrowedMove>,
+    // Places changed in this scope, after their declaration
+    mutated_places: Vec<Place>,
+    // Places passed or mutably borrowed to the parameter with the given
+    // index of a function of the program
+    lent_places: Vec<(Place,String,usize)>,
+    // `let mut` bindings and `&mut` borrows made in this scope
+    mut_bindings: Vec<MutBinding>,
+    mut_borrows: Vec<MutBorrow>,
+    // Values returned by the functions visited in this scope, and the
+    // results of calls kept in this scope
+    returns: Vec<ReturnInfo>,
+    result_uses: Vec<ResultUse>,
+    // Fields of structure literals, and fields of structures changed or
+    // moved out of after the structures are built
+    field_inits: Vec<FieldInit>,
+    field_writes: Vec<(String,String)>,
+    enum_list:&'a HashMap<String,HashMap<String,HashMap<String,Ty>>>,
+    structure_list:&'a HashMap<String,HashMap<String,Ty>>,
+    // Names of the types of the owned fields of each structure
+    field_types:&'a HashMap<String,HashMap<String,String>>,
+    // The type each owned field can borrow and each reference field can own
+    field_forms:&'a HashMap<String,HashMap<String,String>>,
+    // Structures and enums that derive or implement Default
+    default_types:&'a HashSet<String>,
+    // Keys of the structures and enums, for example: shapes::Point
+    type_keys:&'a HashSet<String>,
+    alias_list:&'a HashMap<String,Alias>,
+    signatures:&'a Signatures,
+    // Control flow graph of the function being visited in this scope
+    cfg_builder:Option<CfgBuilder>,
+    // Control flow graphs of the functions defined in this scope
+    cfgs:Vec<Cfg>,
+    // Move analyses of the functions defined in this scope
+    move_analyses:Vec<MoveAnalysis>,
+    // Effects on the parameters of the function being visited in this scope
+    summary_builder:Option<SummaryBuilder>,
+    // Effects on the parameters of the functions defined in this scope
+    summaries:Vec<SummaryBuilder>,
+    // Parameters of the function this scope is the body of, they are
+    // declared by the caller
+    params:Vec<Place>,
+    // Modules around this scope
+    mod_path:Vec<String>,
+    // Type and key prefix of the impl block or trait around this scope
+    self_type:Option<String>,
+    fn_prefix:Option<String>,
 }
 
 // Record all the information of call for defined method
@@ -145,6 +304,111 @@ struct CallInfo{
     receiver: Option<Vec<String>>,
     arguments: Vec<Vec<String>>,
     call_location: usize,
+    // The position of the call in the control flow graph: the block and
+    // the number of points before the call in the block
+    point: Option<(usize,usize)>,
+    // The place passed as each argument, None if an argument is not
+    // a variable
+    places: Vec<Option<Place>>,
+    // Whether each argument is still needed after the call
+    live_after: Vec<bool>,
+    // The variable cloned by each argument, for example: x for x.clone()
+    cloned: Vec<Option<Vec<String>>>,
+    // The function the call is made in
+    caller: Option<String>,
+}
+
+// Record a value cloned from a variable, for example: x.clone()
+#[derive(Debug)]
+struct CloneInfo{
+    var: Vec<String>,
+    place: Option<Place>,
+    // clone, to_owned or to_vec
+    method: String,
+    line: usize,
+    // Where the cloned value goes, for example: passed to `f`
+    target: Option<String>,
+    // The position of the clone in the control flow graph
+    point: Option<(usize,usize)>,
+    // Whether the variable is still needed after the clone
+    live_after: bool,
+}
+
+// Record an owned string created from borrowed data, for example:
+// "a".to_string(), where the borrowed data would do
+#[derive(Debug)]
+struct ConversionInfo{
+    line: usize,
+    // The conversion and the borrowed form to use instead
+    conversion: String,
+    borrowed: String,
+    // Why the owned value is not needed, for example: compared with `==`
+    reason: String,
+    // The function and the index of the parameter the value is passed
+    // to, the value is only not needed if the function only reads it
+    param: Option<(String,usize)>,
+}
+
+// Record a field reached through a mutable reference that is cloned,
+// for example: self.name.clone() in a method taking &mut self
+#[derive(Debug)]
+struct BorrowedMove{
+    line: usize,
+    // The expression that clones the field
+    cloned: String,
+    // The expression that takes the value and leaves another in its place
+    rewritten: String,
+}
+
+// Record a variable declared with `let mut`
+#[derive(Debug)]
+struct MutBinding{
+    place: Place,
+    line: usize,
+}
+
+// Record a mutable borrow, for example: &mut x, which is either kept by
+// a variable or passed to a function of the program
+#[derive(Debug)]
+struct MutBorrow{
+    line: usize,
+    // The borrowed expression, for example: x for &mut x
+    borrowed: String,
+    binding: Option<Place>,
+    callee: Option<(String,usize)>,
+}
+
+// Record a value returned from a function, with the field it is a clone
+// of if the field belongs to a borrowed receiver or parameter, for
+// example: self.name for self.name.clone() in a method taking &self
+#[derive(Debug)]
+struct ReturnInfo{
+    function: String,
+    line: usize,
+    field: Option<String>,
+}
+
+// Record a call of a function of the program whose result is kept, either
+// by a variable or by what it is passed to, returned from or stored in
+#[derive(Debug)]
+struct ResultUse{
+    function: String,
+    line: usize,
+    // The variable bound to the result, None if the result is given away
+    binding: Option<Place>,
+}
+
+// Record the value a field of a structure literal is initialised with
+#[derive(Debug)]
+struct FieldInit{
+    structure: String,
+    field: String,
+    line: usize,
+    // The data the value is cloned from, for example: name for name.clone()
+    cloned: Option<String>,
+    // Whether the value borrows a value created only to be borrowed, for
+    // example: &name.clone() or &format!("{}", x)
+    temporary: bool,
 }
 
 // A data structure used to record the information for each variable
@@ -155,53 +419,77 @@ struct VarInfo {
     var_type: Ty,
     ref_by: Vec<Vec<String>>,
     ref_to:Option<Vec<String>>,
-    moved: bool,
+    // Whether the value is moved at the end of the function
+    moved: MoveState,
     when_to_drop:usize,
+    // Lines before which the value can be dropped, one for each control
+    // flow path on which the value is no longer needed
+    drop_points:Vec<usize>,
+    // Lines the value is moved on, on any path
+    move_lines:Vec<usize>,
     structure:Option<HashMap<String,VarInfo>>,
+    // Name of the type of the value, if it is known
+    type_name:Option<String>,
+    // Whether the variable is a mutable reference, for example: r: &mut T
+    mut_ref:bool,
+    // The kind of the value a reference points to, when it is declared
+    // with a reference type but does not refer to a known variable, for
+    // example: a parameter p: &Point
+    pointee:Option<Ty>,
 }
 
     
 impl <'a> SymbolTable<'a> {
 
-    // Print all the variable information in the symbol table
-    fn debug(&self){
+    // Print all the variable information in the symbol table, and the
+    // control flow graphs if `debug_cfg` is set
+    fn debug(&self, debug_cfg: bool){
         println!("=======================");
         println!("scope: {}-{}", self.scope_start, self.scope_end );
         println!("=======================");
         println!("--------------------------------------------------------");
         
-        for (var,info) in self.var_table.iter(){
-            println!("variable: {}\ndeclared on line: {}, last_used_loc: {:?}\nvar_type: {:?}, moved: {}\nref_to: {:?},ref_by:{:?}, when_to_drop: {:?}",
-             var, info.decl_loc,info.last_used_loc,info.var_type,info.moved,info.ref_to,info.ref_by,info.when_to_drop);
+        for (var,info) in self.sorted_vars(){
+            println!("variable: {}\ndeclared on line: {}, last_used_loc: {:?}\nvar_type: {:?}, moved: {:?}\nref_to: {:?},ref_by:{:?}, when_to_drop: {:?}\ndrop_points: {:?}",
+             var, info.decl_loc,info.last_used_loc,info.var_type,info.moved,info.ref_to,info.ref_by,info.when_to_drop,info.drop_points);
             println!("\nFields:");
             match info.structure{
                 Some(ref structure) => {
-                    for (field_name, field_info) in structure{
+                    let mut fields:Vec<(&String,&VarInfo)> = structure.iter().collect();
+                    fields.sort_by(|a,b| a.0.cmp(b.0));
+                    for (field_name, field_info) in fields{
                         println!("\n-----\"{}\"-----",field_name);
-                        println!("last_used_loc: {:?}, var_type: {:?}\nmoved: {}, ref_to: {:?}\nref_by:{:?}",field_info.last_used_loc,field_info.var_type,field_info.moved,field_info.ref_to,field_info.ref_by);
+                        println!("last_used_loc: {:?}, var_type: {:?}\nmoved: {:?}, ref_to: {:?}\nref_by:{:?}",field_info.last_used_loc,field_info.var_type,field_info.moved,field_info.ref_to,field_info.ref_by);
                     } 
                 },
                 None=> println!("None"),
             }
             println!("--------------------------------------------------------");
         }
+        if debug_cfg{
+            for cfg in &self.cfgs{
+                cfg.debug();
+                println!("--------------------------------------------------------");
+            }
+        }
         // After printing the variable information of a symbol table
         // start to print the variable in formation of its enclosing scope
         let en = & self.enclose_scope;
                 for e in en  {
-                      e.debug();
+                      e.debug(debug_cfg);
           }
 
     }
 
-    // Used to check whether a specific variable's ownership is moved
+    // Used to check whether a specific variable's ownership is moved,
+    // or the ownership of one of its fields may be moved
     fn check_move(&self, var:&str)-> bool{
         let mut is_move = false;
         if let Some(info) = self.var_table.get(var){
-            if !info.moved{
+            if info.moved != MoveState::Moved{
                 if let Some(ref structure) = info.structure{
-                    for(fields, _ ) in structure{
-                        is_move = is_move|check_move_struct(&fields,structure);
+                    for fields in structure.keys(){
+                        is_move |= check_move_struct(fields,structure);
                     }
                 }
             }else{
@@ -211,21 +499,19 @@ impl <'a> SymbolTable<'a> {
         is_move      
     }
 
-    // Used to check wether a specific variable is referred by another variable 
+    // Used to check wether a specific variable, or one of its fields,
+    // is referred by another variable
     fn no_var_ref(&self, var:&str)->bool{
         let mut is_reffered = false;
         if let Some(info) = self.var_table.get(var){
-            if !info.ref_by.is_empty(){
-                if let Some(ref structure) = info.structure{
-                    for(fields, _ ) in structure{
-                        is_reffered = is_reffered|no_var_ref_struct(&fields,structure);
-                    }
+            is_reffered = !info.ref_by.is_empty();
+            if let Some(ref structure) = info.structure{
+                for fields in structure.keys(){
+                    is_reffered |= !no_var_ref_struct(fields,structure);
                 }
-            }else{
-                is_reffered = true;
             }
-        }  
-        is_reffered
+        }
+        !is_reffered
     }
 
     // Turn a span in to a line number to should the location
@@ -237,12 +523,7 @@ impl <'a> SymbolTable<'a> {
     fn record_last_used(&mut self, vars: &Vec<String>, line: usize){
         if let Some(info) = self.var_table.get_mut(&vars[0]){
             if vars.len() == 1{
-                if info.moved {
-                    // Cannot use a moved variable
-                    panic!("Try to move moved value !");
-                } else{
-                    info.last_used_loc = Some(line);
-                }
+                info.last_used_loc = Some(line);
             }else{
                 match &info.var_type{
                     &Ty::NonPrimitive => {
@@ -274,58 +555,55 @@ impl <'a> SymbolTable<'a> {
     // When the walker get into a new scope, build a new symbol table 
     // and bund it to current scope's enclosing scope.
     fn get_in_scope(&mut self,b: &Block){
+        let start = self.codemap.lookup_char_pos(b.span.lo).line;
+        let end = self.codemap.lookup_char_pos(b.span.hi).line;
+        self.get_in_scope_lines(start,end);
+    }
+
+    // Build a new symbol table for a scope that is not a block, for
+    // example: a match arm
+    fn get_in_scope_lines(&mut self, start: usize, end: usize){
         let new =SymbolTable{
             var_table:HashMap::new(),
             enclose_scope:vec![],
-            outer_scope: Some(unsafe{Box::from_raw((self as *mut SymbolTable))}),
+            outer_scope: Some(unsafe{Box::from_raw(self as *mut SymbolTable)}),
             codemap : self.codemap,
-            scope_start : self.codemap.lookup_char_pos(b.span.lo).line,
-            scope_end : self.codemap.lookup_char_pos(b.span.hi).line,     
+            scope_start : start,
+            scope_end : end,     
             fun_records: self.fun_records,
             call_records: HashMap::new(),
+            clone_records: vec![],
+            conversion_records: vec![],
+            borrowed_moves: vec![],
+            mutated_places: vec![],
+            lent_places: vec![],
+            mut_bindings: vec![],
+            mut_borrows: vec![],
+            returns: vec![],
+            result_uses: vec![],
+            field_inits: vec![],
+            field_writes: vec![],
             enum_list: self.enum_list,
-            structure_list:self.structure_list
+            structure_list:self.structure_list,
+            field_types:self.field_types,
+            field_forms:self.field_forms,
+            default_types:self.default_types,
+            type_keys:self.type_keys,
+            alias_list:self.alias_list,
+            signatures:self.signatures,
+            cfg_builder:None,
+            cfgs:vec![],
+            move_analyses:vec![],
+            summary_builder:None,
+            summaries:vec![],
+            params:vec![],
+            mod_path:self.mod_path.clone(),
+            self_type:self.self_type.clone(),
+            fn_prefix:self.fn_prefix.clone(),
         };
         self.enclose_scope.push(Box::new(new));
     }
 
-    // When a variable's ownnership is moved, change its 'moved' state 
-    fn move_ownership(&mut self, vars : &Vec<String>){
-        // If var already been used, print out a error massage 
-        // and terminate
-        if let Some(ref mut info) = self.var_table.get_mut(&vars[0]){
-            // Only NonPrimitive type can be moved
-            match &info.var_type{
-                &Ty::NonPrimitive=>{
-                    if vars.len()==1{
-                        if info.moved {
-                            panic!("Try to move moved value !");
-                        }else {
-                            info.moved = true;
-                        }
-                    }else{
-                        let mut new_var = (*vars).clone();
-                        new_var.remove(0);
-                        if let Some(ref mut structure) = info.structure{
-                            move_ownership_for_struct(structure,&new_var);
-                        }
-                    }
-                },
-                _=>(),
-            }
-        }else{
-            // If the variable can not be find in current scope, search it 
-            //from outer scope
-             match self.outer_scope {
-                 Some(ref mut out) => {
-                     let out = (*out).deref_mut() ;
-                    out.move_ownership(vars);
-                },
-                None => (),
-            }
-        }
-    }
-
     // Record the declared variable into symbol table
     fn local_lhs (&mut self , 
                         pattern:& PatKind,
@@ -338,7 +616,7 @@ impl <'a> SymbolTable<'a> {
                     let location = self.location(span_ident.span);
                     let var_name = vec![span_ident.node.name.as_str().to_string()];
 
-                    let mut info = VarInfo{decl_loc:location , last_used_loc : None, var_type: var_ty ,ref_by: vec![], ref_to: ref_to.clone() , moved: false , when_to_drop: self.scope_end,structure:None};
+                    let mut info = VarInfo{decl_loc:location , last_used_loc : None, var_type: var_ty ,ref_by: vec![], ref_to: ref_to.clone() , moved: MoveState::Init , when_to_drop: self.scope_end,drop_points:vec![],move_lines:vec![],structure:None,type_name:None,mut_ref:false,pointee:None};
                     if !structure.is_empty(){
                         let mut var_structure = HashMap::new();
                         for (field_name,field_resolve) in structure{
@@ -349,9 +627,14 @@ impl <'a> SymbolTable<'a> {
                                     var_type: ty,
                                     ref_by: vec![],
                                     ref_to:field_resolve.ref_to,
-                                    moved: false,
+                                    moved: MoveState::Init,
                                     when_to_drop:self.scope_end,
+                                    drop_points:vec![],
+                                    move_lines:vec![],
                                     structure:None,
+                                    type_name:None,
+                                    mut_ref:false,
+                                    pointee:None,
                                 };
                                 let mut whole_field_name = var_name.clone();
                                 whole_field_name.push(field_name.clone());
@@ -372,6 +655,7 @@ impl <'a> SymbolTable<'a> {
                     }
                         
                     self.var_table.insert(span_ident.node.name.as_str().to_string(),info);
+                    self.record_event(&var_name,Action::Def,location);
 
                     match ref_to{
                         Some(ref_var) => {
@@ -380,10 +664,139 @@ impl <'a> SymbolTable<'a> {
                         None=>(),
                     }                
                 },
+                // Destructuring a tuple, for example: let (a, b) = (x, y);
+                // each sub-pattern is bound to the cooresponding element
+                &PatKind::Tuple(ref pats, None) => {
+                    self.local_lhs_positional(pats, structure);
+                },
+                // Destructuring a tuple structure or an enum variant with payload,
+                // for example: let Point(a, b) = p; or Msg::Text(t) => ...
+                &PatKind::TupleStruct(ref path, ref pats, None) => {
+                    let structure = self.pattern_structure(path, structure);
+                    self.local_lhs_positional(pats, structure);
+                },
+                // Destructuring a structure or a struct-like enum variant,
+                // for example: let Point{x, y} = p; or Msg::Move{x, y} => ...
+                &PatKind::Struct(ref path, ref field_pats, _) => {
+                    let mut structure = self.pattern_structure(path, structure);
+                    for field_pat in field_pats{
+                        let field_name = field_pat.node.ident.name.as_str().to_string();
+                        if let Some(field_resolve) = structure.remove(&field_name){
+                            if let Some(ty) = field_resolve.var_type{
+                                self.local_lhs(&field_pat.node.pat.node, ty, field_resolve.ref_to, field_resolve.structure);
+                            }
+                        }
+                    }
+                },
                 _ => (),
             }
     }
 
+    // Bind each sub-pattern of a tuple pattern to the cooresponding
+    // positional field
+    fn local_lhs_positional(&mut self,
+                                        pats:&Vec<P<Pat>>,
+                                        structure:HashMap<String,Resolver>)
+    {
+        let mut structure = structure;
+        let mut index:usize = 0;
+        for pat in pats{
+            if let Some(field_resolve) = structure.remove(&index.to_string()){
+                if let Some(ty) = field_resolve.var_type{
+                    self.local_lhs(&pat.node, ty, field_resolve.ref_to, field_resolve.structure);
+                }
+            }
+            index+=1;
+        }
+    }
+
+    // Complete the known fields of a destructured value with the fields
+    // recorded for the structure or enum variant named in the pattern
+    fn pattern_structure(&self,
+                                    path:&ast::Path,
+                                    structure:HashMap<String,Resolver>) -> HashMap<String,Resolver>
+    {
+        let mut structure = structure;
+        if let Some(fields) = pre_record::path_fields(path,self.enum_list,self.structure_list,self.alias_list,&self.mod_path,self.type_keys){
+            for (field_name,field_type) in fields{
+                if !structure.contains_key(field_name){
+                    structure.insert(field_name.clone(),Resolver{
+                        var_name:None,
+                        var_type:Some(field_type.clone()),
+                        ref_to:None,
+                        structure:HashMap::new(),
+                        type_name:None,
+                    });
+                }
+            }
+        }
+        structure
+    }
+
+    // Visit a match arm in a new scope, in which the variables bound by
+    // the patterns of the arm are declared
+    fn visit_arm_in_scope(&mut self, arm: &Arm, resolve: &Resolver){
+        let start = self.location(arm.pats[0].span);
+        let end = self.codemap.lookup_char_pos(arm.body.span.hi).line;
+        self.get_in_scope_lines(start,end);
+        let len = self.enclose_scope.len();
+        let cur_scope = &mut self.enclose_scope[len-1];
+        for pat in &arm.pats{
+            cur_scope.bind_pattern(&pat.node,resolve);
+        }
+        visit::walk_arm((*cur_scope).deref_mut(),arm);
+    }
+
+    // Declare the variables bound by a pattern that matches the
+    // value described by the resolver
+    fn bind_pattern(&mut self, pattern: &PatKind, resolve: &Resolver){
+        let var_type = match resolve.var_type{
+            Some(ref ty) => ty.clone(),
+            None => return,
+        };
+        self.local_lhs(pattern,var_type,resolve.ref_to.clone(),resolve.structure.clone());
+    }
+
+    // Get the place passed as an argument, a reference argument passes
+    // the place it refers to
+    fn argument_place(&self, resolve: &Resolver) -> Option<Place>{
+        match resolve.var_name{
+            Some(ref var) => self.place(var),
+            None => match resolve.ref_to{
+                Some(ref var) => self.place(var),
+                None => None,
+            },
+        }
+    }
+
+    // Visit a block in a new scope, in which the variables bound by
+    // the pattern are declared, for example: the block of an if let
+    fn visit_block_with_pattern(&mut self, block: &Block, pattern: &PatKind, resolve: &Resolver){
+        self.get_in_scope(block);
+        let len = self.enclose_scope.len();
+        let cur_scope = &mut self.enclose_scope[len-1];
+        cur_scope.bind_pattern(pattern,resolve);
+        visit::walk_block((*cur_scope).deref_mut(),block);
+    }
+
+    // Visit the body of a function in a new scope, in which the parameters
+    // of the function are declared. The value of the last expression of the
+    // body is returned, if the function returns a value
+    fn visit_fn_body<'v>(&mut self, name: &str, fd: &FnDecl, body: &'v Block){
+        self.get_in_scope(body);
+        let len = self.enclose_scope.len();
+        let cur_scope = &mut self.enclose_scope[len-1];
+        cur_scope.declare_params(name,fd);
+        visit::walk_block((*cur_scope).deref_mut(),body);
+        if let FunctionRetTy::Ty(_) = fd.output{
+            if let Some(stmt) = body.stmts.last(){
+                if let StmtKind::Expr(ref e) = stmt.node{
+                    cur_scope.return_expr(e);
+                }
+            }
+        }
+    }
+
     // Construct the structure for a specific variable
     fn build_struct(&mut self,
                             location:usize,
@@ -400,15 +813,20 @@ impl <'a> SymbolTable<'a> {
                     var_type: ty,
                     ref_by: vec![],
                     ref_to:field_resolve.ref_to,
-                    moved: false,
+                    moved: MoveState::Init,
                     when_to_drop:self.scope_end,
+                    drop_points:vec![],
+                    move_lines:vec![],
                     structure:None,
+                    type_name:None,
+                    mut_ref:false,
+                    pointee:None,
                 };
                 whole_field_name.push(field_name.clone());
               
                 match new_info.ref_to{
                     Some(ref ref_var) => {
-                        self.ref_to_var(ref_var,&whole_field_name);
+                        self.ref_to_var(ref_var,whole_field_name);
                     }
                     None=>(),
                 } 
@@ -435,12 +853,10 @@ impl <'a> SymbolTable<'a> {
                         ref_to_var_struct(structure,&new_var,ref_by);
                     }
                 }
-        } else {
-            if let Some (ref mut outer) = self.outer_scope{
-                outer.ref_to_var(var,ref_by)
-            }else{
-                panic!{"Variable cannot be found!"};
-            }
+        } else if let Some (ref mut outer) = self.outer_scope{
+            outer.ref_to_var(var,ref_by)
+        }else{
+            panic!{"Variable cannot be found!"};
         }
     }
 
@@ -460,98 +876,852 @@ impl <'a> SymbolTable<'a> {
                      var_type=get_var_type_struct(structure,&new_var);
                 }
            }
-        } else {
-            if let Some (ref outer) = self.outer_scope{
-                var_type = outer.get_var_type(var)
-            }else{
-                panic!{"Variable cannot be found!"};
-            }
+        } else if let Some (ref outer) = self.outer_scope{
+            var_type = outer.get_var_type(var)
+        }else{
+            panic!{"Variable cannot be found!"};
         }
         var_type
     }
 
-    fn get_last_used(&self,var: &Vec<String>) ->usize{
-        let mut last_used:usize = 0;
+    // Get the variable a specific reference variable, or a field holding
+    // a reference, refers to.
+    // If this variable cannot be found in current scope,
+    // find it in outer scope
+    fn get_ref_to(&self, var: &Vec<String>) -> Option<Vec<String>>{
         if let Some(info) = self.var_table.get(&var[0]){
             if var.len()==1{
-                if let Some(last_used_loc) = info.last_used_loc{
-                    last_used = last_used_loc;   
-                }
+                return info.ref_to.clone()
+            }
+            let mut new_var = (*var).clone();
+            new_var.remove(0);
+            return match info.structure{
+                Some(ref structure) => get_ref_to_struct(structure,&new_var),
+                None => None,
+            }
+        }
+        match self.outer_scope{
+            Some(ref outer) => outer.get_ref_to(var),
+            None => None,
+        }
+    }
+
+    // Get the line on which a specific variable got its value
+    fn get_decl_loc(&self, var: &str) -> Option<usize>{
+        if let Some(info) = self.var_table.get(var){
+            return Some(info.decl_loc)
+        }
+        match self.outer_scope{
+            Some(ref outer) => outer.get_decl_loc(var),
+            None => None,
+        }
+    }
+
+    // Get the place of a variable or a field, which is identified by the
+    // line the variable got its value on
+    fn place(&self, var: &Vec<String>) -> Option<Place>{
+        match self.get_decl_loc(&var[0]){
+            Some(decl) => Some(Place{ var: var.clone(), decl: decl }),
+            None => None,
+        }
+    }
+
+    // Get the builder of the control flow graph of the function being
+    // visited, which is kept by the scope the function is defined in
+    fn cfg_builder(&mut self) -> Option<&mut CfgBuilder>{
+        if self.cfg_builder.is_some(){
+            return self.cfg_builder.as_mut()
+        }
+        match self.outer_scope{
+            Some(ref mut outer) => outer.cfg_builder(),
+            None => None,
+        }
+    }
+
+    // Change the control flow graph of the function being visited, if any
+    fn cfg<F>(&mut self, f: F) where F: FnOnce(&mut CfgBuilder){
+        if let Some(builder) = self.cfg_builder(){
+            f(builder);
+        }
+    }
+
+    // Add a program point for a variable to the control flow graph
+    fn record_event(&mut self, var: &Vec<String>, action: Action, line: usize){
+        if let Some(place) = self.place(var){
+            let effect = match action{
+                Action::Def => Effect::Mutated,
+                Action::Use => Effect::Read,
+                Action::Move => Effect::Moved,
+            };
+            if effect == Effect::Mutated{
+                self.mark_mutated(&place,line);
+            }
+            if let Some(builder) = self.summary_builder(){
+                builder.record(&place,line,effect);
+            }
+            self.cfg(|c| c.record(line,action,place));
+        }
+    }
+
+    // The position of the next program point in the control flow graph
+    fn cfg_position(&mut self) -> Option<(usize,usize)>{
+        match self.cfg_builder(){
+            Some(builder) => Some(builder.position()),
+            None => None,
+        }
+    }
+
+    // Get the recorder of the effects on the parameters of the function
+    // being visited, which is kept by the scope the function is defined in
+    fn summary_builder(&mut self) -> Option<&mut SummaryBuilder>{
+        if self.summary_builder.is_some(){
+            return self.summary_builder.as_mut()
+        }
+        match self.outer_scope{
+            Some(ref mut outer) => outer.summary_builder(),
+            None => None,
+        }
+    }
+
+    // Record an effect on a variable, if it is a parameter of the function
+    // being visited
+    fn effect(&mut self, var: &Vec<String>, line: usize, effect: Effect){
+        if let Some(place) = self.place(var){
+            if effect == Effect::Mutated{
+                self.mark_mutated(&place,line);
+            }
+            if let Some(builder) = self.summary_builder(){
+                builder.record(&place,line,effect);
+            }
+        }
+    }
+
+    // Record a change of a place, the declaration of a variable does not
+    // change it
+    fn mark_mutated(&mut self, place: &Place, line: usize){
+        if (place.var.len() == 1) & (line <= place.decl){
+            return
+        }
+        self.mutated_places.push(place.clone());
+    }
+
+    // Record an argument that mutably borrows a place for a function of
+    // the program, for example: f(&mut x). Whether the place is changed
+    // depends on what the function does with its parameter.
+    // Return whether the argument is such a borrow
+    fn lend_argument(&mut self, arg: &Expr, function: &str, index: usize, line: usize) -> bool{
+        let inner = match &arg.node{
+            &ExprKind::AddrOf(ast::Mutability::Mutable, ref inner) if self.fun_records.contains_key(function) => inner,
+            _ => return false,
+        };
+        let root = match assigned_root(inner){
+            Some(root) => root,
+            None => return false,
+        };
+        self.effect(&root,line,Effect::Borrowed(function.to_string(),index));
+        self.record_field_write(inner,false);
+        if let Some(place) = self.place(&root){
+            self.lent_places.push((place,function.to_string(),index));
+        }
+        self.mut_borrows.push(MutBorrow{
+            line: line,
+            borrowed: pprust::expr_to_string(inner),
+            binding: None,
+            callee: Some((function.to_string(),index)),
+        });
+        true
+    }
+
+    // Visit the arguments of a call. The borrowed expression of an argument
+    // lent to a function of the program is visited instead of the borrow
+    fn visit_arguments(&mut self, args: &[P<Expr>], lent: &[bool]){
+        let mut index = 0;
+        for arg in args{
+            match &arg.node{
+                &ExprKind::AddrOf(_, ref inner) if lent.get(index) == Some(&true) => self.visit_expr(inner),
+                _ => self.visit_expr(arg),
+            }
+            index+=1;
+        }
+    }
+
+    // Declare the parameters of a function in the scope of its body.
+    // `self` is not a parameter
+    fn declare_params(&mut self, name: &str, fd: &FnDecl){
+        let fun_records = self.fun_records;
+        let params = match fun_records.get(name){
+            Some(info) => &info.params,
+            None => return,
+        };
+        let mut index = 0;
+        for arg in &fd.inputs{
+            if arg.is_self(){
+                continue
+            }
+            let ty = match params.get(index){
+                Some(param) => param.ty.clone(),
+                None => Ty::NonPrimitive,
+            };
+            let type_name = self.type_name(&arg.ty);
+            // A parameter of a structure type, or a reference to one, has
+            // the fields of the structure
+            let structure = self.type_structure(type_name.as_ref());
+            self.local_lhs(&arg.pat.node, ty, None, structure);
+            let pointee = pointee_type(&arg.ty,self.alias_list);
+            let by_value = pointee.is_none();
+            self.set_type_info(&arg.pat.node,type_name,mut_ref_type(&arg.ty),pointee);
+            let place = match &arg.pat.node{
+                &PatKind::Ident(_, ref span_ident, _) => self.place(&vec![span_ident.node.name.as_str().to_string()]),
+                _ => None,
+            };
+            // A parameter that is not a variable cannot be used by name
+            let place = place.unwrap_or(Place{ var: vec!["_".to_string()], decl: 0 });
+            self.params.push(place.clone());
+            if let Some(builder) = self.summary_builder(){
+                builder.add_param(place,by_value);
+            }
+            index+=1;
+        }
+    }
+
+    // The value of an expression is returned from the function
+    fn return_expr(&mut self, e: &Expr){
+        self.record_clone(e,Some("returned".to_string()));
+        self.record_borrowed_move(e);
+        self.record_result_use(e,None);
+        let line = self.codemap.lookup_char_pos(e.span.hi).line;
+        if let Some(function) = self.summary_builder().map(|builder| builder.name().to_string()){
+            let field = self.cloned_field(&function,e,false).map(|(var, fields)| format!("{}.{}",var,fields.join(".")));
+            self.returns.push(ReturnInfo{ function: function, line: line, field: field });
+        }
+        let resolve = resolve::resolve_expr(e,self.enum_list,self.fun_records,self,self.structure_list);
+        let line = self.codemap.lookup_char_pos(e.span.hi).line;
+        self.change_and_used(&resolve,line);
+        if let Some(ref var) = resolve.var_name{
+            self.effect(var,line,Effect::Returned);
+        }
+    }
+
+    // Get the key of the function called by a path, for example: parse,
+    // util::parse, Point::new or Self::new. None if the function is not
+    // defined in the program
+    fn function_key(&self, segments: &Vec<String>) -> Option<String>{
+        let mut segments = segments.clone();
+        if (segments.len() > 1) & (segments[0] == "self"){
+            segments.remove(0);
+        }
+        if segments.len() == 2{
+            let ty = if segments[0] == "Self"{
+                self.self_type.clone()
             }else{
-                let mut new_var = (*var).clone();
-                new_var.remove(0);
-                if let Some(ref structure) = info.structure{
-                    last_used=get_last_used_struct(structure,&new_var);
+                Some(pre_record::type_key(&pre_record::resolve_name(&segments[0],self.alias_list),&self.mod_path,self.type_keys))
+            };
+            if let Some(ty) = ty{
+                if let Some(key) = self.method_key(Some(&ty),&segments[1]){
+                    return Some(key)
                 }
             }
-        }else {
-            if let Some (ref outer) = self.outer_scope{
-                 last_used = outer.get_last_used(var);
-            }else{
-                panic!{"Variable cannot be found!"};
+        }
+        let last = segments.len()-1;
+        segments[last] = pre_record::resolve_name(&segments[last],self.alias_list);
+        let local = pre_record::qualify(&self.mod_path,&segments.join("::"));
+        if self.fun_records.contains_key(&local){
+            return Some(local)
+        }
+        let key = segments.join("::");
+        if self.fun_records.contains_key(&key){
+            return Some(key)
+        }
+        // A function imported from a module by its name
+        if segments.len() == 1{
+            let suffix = "::".to_string() + &segments[0];
+            let candidates:Vec<&String> = self.fun_records.iter()
+                            .filter(|&(key,info)| info.self_ty.is_none() & key.ends_with(&suffix))
+                            .map(|(key,_)| key).collect();
+            if candidates.len() == 1{
+                return Some(candidates[0].clone())
             }
         }
+        None
+    }
+
+    // Get the key of a method called on a receiver of the given type.
+    // When the type of the receiver is not known, a method of the program
+    // is only chosen if no builtin method has the same name and the name
+    // belongs to only one type
+    fn method_key(&self, type_name: Option<&String>, method: &str) -> Option<String>{
+        match type_name{
+            Some(ty) => {
+                let key = ty.to_string() + "::" + method;
+                if self.fun_records.contains_key(&key){
+                    return Some(key)
+                }
+                // A method of a trait implemented for the type
+                let prefix = "<".to_string() + ty + " as ";
+                let suffix = ">::".to_string() + method;
+                let mut candidates:Vec<&String> = self.fun_records.keys()
+                                .filter(|key| key.starts_with(&prefix) & key.ends_with(&suffix)).collect();
+                candidates.sort();
+                candidates.first().map(|key| (*key).clone())
+            },
+            None => {
+                if self.signatures.method(method,&Ty::NonPrimitive,None).is_some(){
+                    return None
+                }
+                let suffix = "::".to_string() + method;
+                let candidates:Vec<&String> = self.fun_records.iter()
+                                .filter(|&(key,info)| info.self_ty.is_some() & key.ends_with(&suffix))
+                                .map(|(key,_)| key).collect();
+                if candidates.len() == 1 { Some(candidates[0].clone()) } else { None }
+            },
+        }
+    }
+
+    // The key of the structure or enum a type expression of this scope
+    // names, other types are named as they are
+    fn type_name(&self, ty: &P<ast::Ty>) -> Option<String>{
+        pre_record::type_name(ty,self.alias_list,&self.mod_path,self.type_keys)
+    }
+
+    // The variables of this scope in the order they are declared, so that
+    // what is printed about them does not depend on the order of the table
+    fn sorted_vars(&self) -> Vec<(&String,&VarInfo)>{
+        let mut vars:Vec<(&String,&VarInfo)> = self.var_table.iter().collect();
+        vars.sort_by(|a,b| (a.1.decl_loc,a.0).cmp(&(b.1.decl_loc,b.0)));
+        vars
+    }
 
-        last_used
+    // The fields of a structure of the program with their types, as the
+    // structure of a value of the type
+    fn type_structure(&self, type_name: Option<&String>) -> HashMap<String,Resolver>{
+        let mut structure = HashMap::new();
+        if let Some(fields) = type_name.and_then(|name| self.structure_list.get(name)){
+            for (field_name,field_type) in fields{
+                structure.insert(field_name.clone(),Resolver{
+                    var_name:None,
+                    var_type:Some(field_type.clone()),
+                    ref_to:None,
+                    structure:HashMap::new(),
+                    type_name:None,
+                });
+            }
+        }
+        structure
     }
 
-    // The the line on which a specific variable will get out of scope
-    fn get_when_drop(&self, var: &[String], ref_to: &str ) -> usize{
+    // Get the name of the type of a variable, if it is known
+    fn get_type_name(&self, var: &Vec<String>) -> Option<String>{
         if let Some(info) = self.var_table.get(&var[0]){
-           if let Some( _ ) = info.ref_to{
-               return  info.when_to_drop
-           }
+            return if var.len() == 1 { info.type_name.clone() } else { None }
         }
-        let mut when_drop = 0;
-       for scope in &self.enclose_scope{
-             when_drop = scope.get_when_drop(var , ref_to)
+        match self.outer_scope{
+            Some(ref outer) => outer.get_type_name(var),
+            None => None,
         }
-        when_drop      
     }
 
-    // If there are variables that are used insid a if statement, record
-    // there last used location as the last line of the if statement
-    fn record_last_used_for_if(&mut self, start: usize, end: usize, scope_num:usize){
-        let len = self.enclose_scope.len();
-        let mut count = 1;
-        while count<scope_num+1 {
-            let ref mut cur_scope = self.enclose_scope[len-count];
-            for info in cur_scope.var_table.values_mut(){
-                match info.last_used_loc{
-                    Some(loc)=> {
-                        if (loc >= start) & (loc <= end) {
-                            if info.decl_loc< start{
-                                info.last_used_loc = Some(end)
-                            }
-                        }
-                    },
-                    None =>(),
+    // Record the name of the type of the variable declared by a pattern,
+    // whether it is a mutable reference and the kind of what it points to
+    fn set_type_info(&mut self, pattern: &PatKind, type_name: Option<String>, mut_ref: bool, pointee: Option<Ty>){
+        if let &PatKind::Ident(_, ref span_ident, _) = pattern{
+            if let Some(info) = self.var_table.get_mut(&span_ident.node.name.as_str().to_string()){
+                info.type_name = type_name;
+                info.mut_ref = mut_ref;
+                info.pointee = pointee;
+            }
+        }
+    }
+
+    // Record a `let mut` binding, and a mutable borrow kept by a variable,
+    // for example: let r = &mut x;
+    fn record_mut_binding(&mut self, pattern: &PatKind, init: &Expr, line: usize){
+        let (mode, name) = match pattern{
+            &PatKind::Ident(mode, ref span_ident, None) => (mode, span_ident.node.name.as_str().to_string()),
+            _ => return,
+        };
+        let place = match self.place(&vec![name]){
+            Some(place) => place,
+            None => return,
+        };
+        if let ast::BindingMode::ByValue(ast::Mutability::Mutable) = mode{
+            self.mut_bindings.push(MutBinding{ place: place.clone(), line: line });
+        }
+        if let ExprKind::AddrOf(ast::Mutability::Mutable, ref inner) = init.node{
+            self.mut_borrows.push(MutBorrow{
+                line: line,
+                borrowed: pprust::expr_to_string(inner),
+                binding: Some(place),
+                callee: None,
+            });
+        }
+    }
+
+    // Whether a variable is a mutable reference
+    fn is_mut_ref(&self, var: &str) -> bool{
+        if let Some(info) = self.var_table.get(var){
+            return info.mut_ref
+        }
+        match self.outer_scope{
+            Some(ref outer) => outer.is_mut_ref(var),
+            None => false,
+        }
+    }
+
+    // The kind of the value a variable declared with a reference type
+    // points to, if it is known
+    fn get_pointee(&self, var: &str) -> Option<Ty>{
+        if let Some(info) = self.var_table.get(var){
+            return info.pointee.clone()
+        }
+        match self.outer_scope{
+            Some(ref outer) => outer.get_pointee(var),
+            None => None,
+        }
+    }
+
+    // The expression that takes the value of a field out and leaves another
+    // one in its place, if the type of the field offers one: an Option
+    // leaves None, a Default type its default value and a type with a
+    // `new()` constructor a new value
+    fn take_rewrite(&self, place: &str, field_type: &str) -> Option<String>{
+        if String::eq(&field_type.to_string(),"Option"){
+            return Some(format!("{}.take()",place))
+        }
+        if builtin::has_default(field_type) | self.default_types.contains(field_type){
+            return Some(format!("std::mem::take(&mut {})",place))
+        }
+        let constructor = field_type.to_string() + "::new";
+        match self.fun_records.get(&constructor){
+            Some(info) if info.input == 0 => Some(format!("std::mem::replace(&mut {}, {}())",place,constructor)),
+            _ => None,
+        }
+    }
+
+    // Record a clone of a field reached through a mutable reference,
+    // for example: `self.name.clone()` in a method taking &mut self.
+    // The value can be taken out instead if another one can be left in
+    // its place
+    fn record_borrowed_move(&mut self, e: &Expr){
+        // A field a value is moved out of needs to own the value
+        self.record_field_write(e,true);
+        let function = match self.summary_builder().map(|builder| builder.name().to_string()){
+            Some(function) => function,
+            None => return,
+        };
+        let (var, fields) = match self.cloned_field(&function,e,true){
+            Some(path) => path,
+            None => return,
+        };
+        let mut type_name = if String::eq(&var,"self"){
+            self.self_type.clone()
+        }else{
+            self.get_type_name(&vec![var.clone()])
+        };
+        // Only the fields that own a value which is not copied are recorded
+        let field_types = self.field_types;
+        for field in &fields{
+            type_name = match type_name.and_then(|ty| field_types.get(&ty)).and_then(|types| types.get(field)){
+                Some(ty) => Some(ty.clone()),
+                None => return,
+            };
+        }
+        let field_type = match type_name{
+            Some(ty) => ty,
+            None => return,
+        };
+        let place = format!("{}.{}",var,fields.join("."));
+        let rewritten = match self.take_rewrite(&place,&field_type){
+            Some(rewritten) => rewritten,
+            None => return,
+        };
+        let line = self.codemap.lookup_char_pos(e.span.hi).line;
+        self.borrowed_moves.push(BorrowedMove{ line: line, cloned: pprust::expr_to_string(e), rewritten: rewritten });
+    }
+
+    // Record a field of a structure that is changed, or that a value is
+    // moved out of, after the structure is built. A field whose value is
+    // copied is not changed by moving it
+    fn record_field_write(&mut self, e: &Expr, moved: bool){
+        let (var, fields) = match field_path(e){
+            Some(path) => path,
+            None => return,
+        };
+        let structure = if String::eq(&var,"self"){
+            self.self_type.clone()
+        }else{
+            self.get_type_name(&vec![var])
+        };
+        let structure = match structure{
+            Some(structure) => structure,
+            None => return,
+        };
+        if moved && !self.field_types.get(&structure).map_or(false, |types| types.contains_key(&fields[0])){
+            return
+        }
+        self.field_writes.push((structure,fields[0].clone()));
+    }
+
+    // Record the values the fields of a structure literal are initialised
+    // with, for example: Person{ name: name.clone() }
+    fn record_field_inits(&mut self, path: &ast::Path, fields: &[ast::Field]){
+        let name = match path.segments.last(){
+            Some(segment) => segment.identifier.name.as_str().to_string(),
+            None => return,
+        };
+        let structure = if String::eq(&name,"Self"){
+            match self.self_type{
+                Some(ref ty) => ty.clone(),
+                None => return,
+            }
+        }else{
+            pre_record::path_type_key(path,self.alias_list,&self.mod_path,self.type_keys)
+        };
+        if !self.structure_list.contains_key(&structure){
+            return
+        }
+        for field in fields{
+            let cloned = match clone_call(&field.expr).or_else(|| owned_conversion(&field.expr)){
+                Some((_, receiver)) => match receiver.node{
+                    // A literal is not data that lives longer than the structure
+                    ExprKind::Lit(_) => None,
+                    _ => Some(pprust::expr_to_string(&field.expr)),
+                },
+                None => None,
+            };
+            let temporary = match &field.expr.node{
+                &ExprKind::AddrOf(_, ref inner) => match inner.node{
+                    ExprKind::Call(..) | ExprKind::MethodCall(..) | ExprKind::Mac(_) => true,
+                    _ => false,
+                },
+                _ => false,
+            };
+            let line = self.codemap.lookup_char_pos(field.span.hi).line;
+            self.field_inits.push(FieldInit{
+                structure: structure.clone(),
+                field: field.ident.node.name.as_str().to_string(),
+                line: line,
+                cloned: cloned,
+                temporary: temporary,
+            });
+        }
+    }
+
+    // Whether a value passed as the argument with the given index of a
+    // function is copied, because the parameter is primitive (a generic
+    // type bounded by Copy is classified as primitive)
+    fn copied_param(&self, function: &str, index: usize) -> bool{
+        if let Some(info) = self.fun_records.get(function){
+            if let Some(param) = info.params.get(index){
+                return match param.ty{
+                    Ty::Primitive => true,
+                    _ => false,
                 }
             }
-            match self.outer_scope{
-                    Some(ref mut table)=>{
-                        let sym_table = (*table).deref_mut();
-                        sym_table.record_last_used_for_if(start,end,scope_num)
-                   },
-                     None =>(),
-             }
-             count+=1;
         }
+        false
     }
 
-    fn record_call_loc_for_if(&mut self, start: usize, end: usize,scope_num:usize){
-        let len = self.enclose_scope.len();
-        let mut count = 1;
-        while count<scope_num+1 {
-            let ref mut cur_scope = self.enclose_scope[len-count];        
-            for infos in cur_scope.call_records.values_mut(){
-                for info in infos{
-                     if (info.call_location >= start) & (info.call_location <= end) {
-                           info.call_location = end
+    // Record a value cloned from a variable by the expression, for example:
+    // x.clone(), x.to_owned() or x.to_vec(). Only a variable that owns its
+    // value could be moved instead
+    fn record_clone(&mut self, e: &Expr, target: Option<String>){
+        let (method, receiver) = match clone_call(e){
+            Some(call) => call,
+            None => return,
+        };
+        let resolve = resolve::resolve_expr(receiver,self.enum_list,self.fun_records,self,self.structure_list);
+        let var = match resolve.var_name{
+            Some(var) => var,
+            None => return,
+        };
+        if String::eq(&var[0],"self"){
+            return
+        }
+        match (self.get_var_type(&vec![var[0].clone()]), self.get_var_type(&var)){
+            (Ty::NonPrimitive, Ty::NonPrimitive) => (),
+            _ => return,
+        }
+        let line = self.codemap.lookup_char_pos(e.span.hi).line;
+        let place = self.place(&var);
+        let point = self.cfg_position();
+        self.clone_records.push(CloneInfo{
+            var: var,
+            place: place,
+            method: method,
+            line: line,
+            target: target,
+            point: point,
+            live_after: true,
+        });
+    }
+
+    // Record an owned string created by the expression that is only needed
+    // in borrowed form. The borrowed form of a String variable is a
+    // reference to it when it replaces a reference
+    fn record_conversion(&mut self, e: &Expr, reference: bool, reason: String, param: Option<(String,usize)>){
+        let (method, receiver) = match owned_conversion(e){
+            Some(conversion) => conversion,
+            None => return,
+        };
+        let borrowed = match &receiver.node{
+            &ExprKind::Lit(ref lit) => match lit.node{
+                ast::LitKind::Str(..) => pprust::expr_to_string(receiver),
+                _ => return,
+            },
+            _ => {
+                let resolve = resolve::resolve_expr(receiver,self.enum_list,self.fun_records,self,self.structure_list);
+                let owned = match resolve.var_type{
+                    Some(Ty::NonPrimitive) => true,
+                    _ => false,
+                };
+                match resolve.type_name.as_ref().map(|name| name.as_str()){
+                    Some("String") if reference & owned => format!("&{}",pprust::expr_to_string(receiver)),
+                    Some("str") | Some("String") => pprust::expr_to_string(receiver),
+                    _ => return,
+                }
+            },
+        };
+        let line = self.codemap.lookup_char_pos(e.span.hi).line;
+        self.conversion_records.push(ConversionInfo{
+            line: line,
+            conversion: format!("{}.{}()",pprust::expr_to_string(receiver),method),
+            borrowed: borrowed,
+            reason: reason,
+            param: param,
+        });
+    }
+
+    // Record an argument that converts a string only to pass it, for
+    // example: f(&s.to_string()) or f("a".to_string()) when f only reads
+    // its parameter
+    fn record_argument_conversion(&mut self, arg: &Expr, function: &str, index: usize){
+        // A parameter declared as &String does not accept the borrowed form
+        let fun_records = self.fun_records;
+        if let Some(&pre_record::ParamInfo{ general: Some(_), .. }) = fun_records.get(function).and_then(|info| info.params.get(index)){
+            return
+        }
+        match &arg.node{
+            &ExprKind::AddrOf(ast::Mutability::Immutable, ref inner) =>
+                self.record_conversion(inner,true,format!("only borrowed by `{}`",function),None),
+            _ => if self.fun_records.contains_key(function){
+                self.record_conversion(arg,true,format!("passed to `{}`",function),Some((function.to_string(),index)));
+            },
+        }
+    }
+
+    // Record the strings converted to owned strings only to be formatted,
+    // for example: println!("{}", s.to_string())
+    fn record_formatted_conversions(&mut self, mac: &Mac){
+        let segments = &mac.node.path.segments;
+        let name = match segments.last(){
+            Some(segment) => segment.identifier.name.as_str().to_string(),
+            None => return,
+        };
+        match name.as_str(){
+            "format" | "print" | "println" | "eprint" | "eprintln" | "write" | "writeln" | "panic" => (),
+            _ => return,
+        }
+        let line = self.location(mac.span);
+        let trees:Vec<TokenTree> = mac.node.stream().into_trees().collect();
+        let mut index = 0;
+        while index + 3 < trees.len(){
+            let window = &trees[index..index+4];
+            index+=1;
+            // A field or a method result is not converted from a variable
+            if index > 1{
+                if let TokenTree::Token(_, Token::Dot) = trees[index-2]{
+                    continue
+                }
+            }
+            let method = match (&window[1],&window[2],&window[3]){
+                (&TokenTree::Token(_, Token::Dot), &TokenTree::Token(_, Token::Ident(method)), &TokenTree::Delimited(_, ref args))
+                    if (args.delim == DelimToken::Paren) && args.stream().is_empty() => method.name.as_str().to_string(),
+                _ => continue,
+            };
+            if (method != "to_string") & (method != "to_owned"){
+                continue
+            }
+            let receiver = match &window[0]{
+                &TokenTree::Token(_, ref token @ Token::Literal(token::Lit::Str_(_), _)) => pprust::token_to_string(token),
+                &TokenTree::Token(_, Token::Ident(ident)) => {
+                    let var = vec![ident.name.as_str().to_string()];
+                    match self.get_type_name(&var).as_ref().map(|name| name.as_str()){
+                        Some("str") | Some("String") => var[0].clone(),
+                        _ => continue,
                     }
+                },
+                _ => continue,
+            };
+            self.conversion_records.push(ConversionInfo{
+                line: line,
+                conversion: format!("{}.{}()",receiver,method),
+                borrowed: receiver,
+                reason: format!("formatted by `{}!`",name),
+                param: None,
+            });
+        }
+    }
+
+    // Get the variable and the field path cloned by an expression if the
+    // field belongs to the borrowed receiver of the function or to a
+    // parameter that is a reference, for example: self and [name] for
+    // self.name.clone(). `mutable` asks for a mutable borrow
+    fn cloned_field(&self, function: &str, e: &Expr, mutable: bool) -> Option<(String,Vec<String>)>{
+        let receiver = match clone_call(e).or_else(|| owned_conversion(e)){
+            Some((_, receiver)) => receiver,
+            None => return None,
+        };
+        let (var, fields) = match field_path(receiver){
+            Some(path) => path,
+            None => return None,
+        };
+        let borrowed = if String::eq(&var,"self"){
+            match self.fun_records.get(function).and_then(|info| info.receiver.clone()){
+                Some(Mode::MutRef) => true,
+                Some(Mode::Ref) => !mutable,
+                _ => false,
+            }
+        }else if mutable{
+            self.is_mut_ref(&var)
+        }else{
+            let root = vec![var.clone()];
+            match self.get_decl_loc(&var){
+                Some(_) => match self.get_var_type(&root){
+                    Ty::Ref => true,
+                    _ => false,
+                },
+                None => false,
+            }
+        };
+        if borrowed{
+            Some((var,fields))
+        }else{
+            None
+        }
+    }
+
+    // Record the result of a call of a function of the program that is
+    // kept by a variable, or given away if there is no variable
+    fn record_result_use(&mut self, e: &Expr, binding: Option<Place>){
+        let function = match &e.node{
+            &ExprKind::Call(ref fun_name, _) => match &fun_name.node{
+                &ExprKind::Path(_, ref p) => {
+                    let segments:Vec<String> = p.segments.iter().map(|seg| seg.identifier.name.as_str().to_string()).collect();
+                    self.function_key(&segments)
+                },
+                _ => None,
+            },
+            &ExprKind::MethodCall(ref name, _, ref args) => {
+                let resolve = resolve::resolve_expr(&args[0],self.enum_list,self.fun_records,self,self.structure_list);
+                self.method_key(resolve.type_name.as_ref(),&name.node.name.as_str())
+            },
+            _ => None,
+        };
+        if let Some(function) = function{
+            let line = self.codemap.lookup_char_pos(e.span.hi).line;
+            self.result_uses.push(ResultUse{ function: function, line: line, binding: binding });
+        }
+    }
+
+    // Get the variable cloned by an expression, for example: x for x.clone()
+    fn cloned_var(&self, e: &Expr) -> Option<Vec<String>>{
+        match clone_call(e){
+            Some((_, receiver)) => resolve::resolve_expr(receiver,self.enum_list,self.fun_records,self,self.structure_list).var_name,
+            None => None,
+        }
+    }
+
+    // Pass an argument to a function. An argument of a function of the
+    // program is summarized by what the function does with its parameter
+    fn pass_argument(&mut self, resolve: &Resolver, function: &str, index: usize, line: usize){
+        match resolve.var_name{
+            Some(ref var) if self.copied_param(function,index) => self.use_var(var,line),
+            _ => self.change_and_used(resolve,line),
+        }
+        if let Some(ref var) = resolve.var_name{
+            if self.fun_records.contains_key(function){
+                self.effect(var,line,Effect::Passed(function.to_string(),index));
+                if let Some(place) = self.place(var){
+                    self.lent_places.push((place,function.to_string(),index));
                 }
-            } 
-            count+=1;   
+            }else if self.is_mut_ref(&var[0]){
+                // A function outside of the program may change what a
+                // mutable reference refers to
+                self.effect(var,line,Effect::Mutated);
+            }
+        }
+    }
+
+    // Get the first line inside a block, 0 if the block is on one line
+    fn inner_line(&self, span: Span) -> usize{
+        let start = self.codemap.lookup_char_pos(span.lo).line;
+        let end = self.codemap.lookup_char_pos(span.hi).line;
+        if start < end { start+1 } else { 0 }
+    }
+
+    // A variable is used (read or borrowed) on the given line
+    fn use_var(&mut self, var: &Vec<String>, line: usize){
+        self.record_event(var,Action::Use,line);
+        self.record_last_used(var,line);
+    }
+
+    // The value of a place expression is read on the given line, for
+    // example: the condition of an if expression, an operand of a
+    // comparison or *r in *r + 1
+    fn read_place(&mut self, e: &Expr, line: usize){
+        if let Some(root) = assigned_root(e){
+            self.use_var(&root,line);
+        }
+    }
+
+    // The ownership of a variable is moved on the given line. Whether it
+    // is moved afterwards depends on the path taken, which is found by
+    // the move analysis once the whole function is visited
+    fn move_var(&mut self, var: &Vec<String>, line: usize){
+        self.record_event(var,Action::Move,line);
+        self.record_last_used(var,line);
+    }
+
+    // Record the drop points and the move states of the variables and
+    // whether the arguments of the calls are needed after the calls, from
+    // the dataflow analyses of the control flow graph of the function
+    fn apply_dataflow(&mut self,
+                                cfg: &Cfg,
+                                liveness: &Liveness,
+                                drops: &HashMap<Place,Vec<usize>>,
+                                moves: &MoveAnalysis)
+    {
+        for (var,info) in &mut self.var_table{
+            let decl = info.decl_loc;
+            set_dataflow(&vec![var.clone()],decl,info,drops,moves);
+        }
+        for infos in self.call_records.values_mut(){
+            for info in infos{
+                if let Some((block,index)) = info.point{
+                    info.live_after = info.places.iter().map(|place| match place{
+                        &Some(ref place) => liveness.live_at(cfg,block,index,place),
+                        &None => false,
+                    }).collect();
+                }
+            }
+        }
+        for info in &mut self.clone_records{
+            if let (Some((block,index)), Some(ref place)) = (info.point, info.place.clone()){
+                // The variable is used by the clone itself, which is on
+                // the same line
+                let points = match cfg.blocks.get(block){
+                    Some(block) => &block.points,
+                    None => continue,
+                };
+                let mut index = index;
+                while (index < points.len()) && (points[index].line == info.line){
+                    index+=1;
+                }
+                info.live_after = liveness.live_at(cfg,block,index,place);
+            }
+        }
+        for scope in &mut self.enclose_scope{
+            scope.apply_dataflow(cfg,liveness,drops,moves);
         }
     }
 
@@ -569,10 +1739,8 @@ impl <'a> SymbolTable<'a> {
                     re=check_var_struct(structure,&new_var);
                 }
             }
-        }else{
-            if let Some(ref outer) = self.outer_scope{
-                re = outer.check_var(var);
-            }
+        }else if let Some(ref outer) = self.outer_scope{
+            re = outer.check_var(var);
         }
         re
     }
@@ -589,14 +1757,15 @@ impl <'a> SymbolTable<'a> {
             }else{                
                 let mut new_var = (*var).clone();
                 new_var.remove(0);
+                if old_info.structure.is_none(){
+                    old_info.structure = Some(HashMap::new());
+                }
                 if let Some(ref mut structure) = old_info.structure{
                     change_var_info_struct(&new_var,structure,info);
                 }
             }
-        }else{
-            if let Some(ref mut outer) = self.outer_scope{
-                outer.change_var_info(var,info);
-            }
+        }else if let Some(ref mut outer) = self.outer_scope{
+            outer.change_var_info(var,info);
         }
     }
 
@@ -612,7 +1781,17 @@ impl <'a> SymbolTable<'a> {
                             )
     {
         let location = self.location(span);
-        let mut info = VarInfo{decl_loc:location , last_used_loc : None, var_type: var_ty ,ref_by: vec![], ref_to: ref_to.clone() , moved: false , when_to_drop: self.scope_end,structure:None};
+        // The value assigned is a new place, the variable assigned to is
+        // changed but keeps its type
+        if let Some(place) = self.place(var){
+            self.mark_mutated(&place,location);
+        }
+        let (type_name, mut_ref, pointee) = if var.len() == 1 {
+            (self.get_type_name(var), self.is_mut_ref(&var[0]), self.get_pointee(&var[0]))
+        } else {
+            (None, false, None)
+        };
+        let mut info = VarInfo{decl_loc:location , last_used_loc : None, var_type: var_ty ,ref_by: vec![], ref_to: ref_to.clone() , moved: MoveState::Init , when_to_drop: self.scope_end,drop_points:vec![],move_lines:vec![],structure:None,type_name:type_name,mut_ref:mut_ref,pointee:pointee};
         if !structure.is_empty(){
             let mut var_structure = HashMap::new();
             for (field_name,field_resolve) in structure{
@@ -623,9 +1802,14 @@ impl <'a> SymbolTable<'a> {
                         var_type: ty,
                         ref_by: vec![],
                         ref_to:field_resolve.ref_to,
-                        moved: false,
+                        moved: MoveState::Init,
                         when_to_drop:self.scope_end,
+                        drop_points:vec![],
+                        move_lines:vec![],
                         structure:None,
+                        type_name:None,
+                        mut_ref:false,
+                        pointee:None,
                     };
                     let mut whole_field_name = vec![var[0].clone()];
                     whole_field_name.push(field_name.clone());
@@ -645,14 +1829,13 @@ impl <'a> SymbolTable<'a> {
             } 
             info.structure = Some(var_structure);
         }
-        if self.check_var(var){
+        // A field that is not recorded yet, for example of a value whose
+        // type is not known, is recorded when it is assigned. A field of a
+        // variable that is not known is left out
+        if self.check_var(&vec![var[0].clone()]){
             self.change_var_info(var,info);
-        }else{
-            if var.len() ==1{
-                self.var_table.insert(var[0].clone(),info); 
-            }else{
-                panic!{"Unresolved field!"};
-            }
+        }else if var.len() ==1{
+            self.var_table.insert(var[0].clone(),info); 
         }
         match ref_to{
             Some(ref_var) => {
@@ -660,6 +1843,7 @@ impl <'a> SymbolTable<'a> {
             }
             None=>(),
         }
+        self.record_event(var,Action::Def,location);
     }   
 
     // when a specific virable is used in a statement, change its last used
@@ -669,22 +1853,26 @@ impl <'a> SymbolTable<'a> {
                                     line: usize)
     {
         if let Some(ref var_name) = resolver.var_name{
-            self.record_last_used(var_name,line);
             match self.get_var_type (var_name) {
-                Ty::NonPrimitive =>self.move_ownership(var_name),
-                 _=>() ,                          
+                Ty::NonPrimitive =>self.move_var(var_name,line),
+                 _=>self.use_var(var_name,line) ,                          
             }
         }else{                    
             if let Some(ref var_type) = resolver.var_type{
                 match var_type{
                     &Ty::Ref=> {
                         if let Some(ref ref_to) = resolver.ref_to{
-                            self.record_last_used(ref_to,line)
+                            self.use_var(ref_to,line)
                         }
                     },
                     _=>(),
                 }
             }
+            // Elements of a tuple expression are used (and moved) by
+            // the expression as well
+            for field_resolver in resolver.structure.values(){
+                self.change_and_used(field_resolver,line);
+            }
         }             
     }
 }
@@ -708,6 +1896,81 @@ impl <'a> SymbolTable<'a> {
 // }
 
 
+// The variable and the fields of a field expression, for example:
+// (self, [name]) for self.name. None if the expression is not a field
+// of a variable
+fn field_path(e: &Expr) -> Option<(String,Vec<String>)>{
+    let mut fields = vec![];
+    let mut root = e;
+    loop{
+        match &root.node{
+            &ExprKind::Field(ref inner, ref ident) => {
+                fields.insert(0,ident.node.name.as_str().to_string());
+                root = inner;
+            },
+            &ExprKind::TupField(ref inner, ref index) => {
+                fields.insert(0,index.node.to_string());
+                root = inner;
+            },
+            &ExprKind::Paren(ref inner) => root = inner,
+            _ => break,
+        }
+    }
+    match &root.node{
+        &ExprKind::Path(None, ref p) if !fields.is_empty() & (p.segments.len() == 1) =>
+            Some((p.segments[0].identifier.name.as_str().to_string(),fields)),
+        _ => None,
+    }
+}
+
+// Whether a type is a mutable reference, for example: &mut T or the type
+// of `&mut self`
+fn mut_ref_type(ty: &P<ast::Ty>) -> bool{
+    match &ty.node{
+        &ast::TyKind::Rptr(_, ref mut_ty) => mut_ty.mutbl == ast::Mutability::Mutable,
+        _ => false,
+    }
+}
+
+// The kind of the value a reference type points to, None if the type is
+// not a reference
+fn pointee_type(ty: &P<ast::Ty>, aliases: &HashMap<String,Alias>) -> Option<Ty>{
+    match &ty.node{
+        &ast::TyKind::Rptr(_, ref mut_ty) => Some(pre_record::classify(&mut_ty.ty,aliases)),
+        _ => None,
+    }
+}
+
+// The method and the receiver of an expression that converts borrowed
+// data to an owned value, for example: s.to_string() or s.to_owned()
+fn owned_conversion(e: &Expr) -> Option<(String,&Expr)>{
+    match &e.node{
+        &ExprKind::MethodCall(ref name, _, ref args) if args.len() == 1 => {
+            let method = name.node.name.as_str().to_string();
+            match method.as_str(){
+                "to_string" | "to_owned" => Some((method.clone(),&args[0])),
+                _ => None,
+            }
+        },
+        _ => None,
+    }
+}
+
+// The method and the receiver of an expression that clones a value,
+// for example: x.clone(), x.to_owned() or x.to_vec()
+fn clone_call(e: &Expr) -> Option<(String,&Expr)>{
+    match &e.node{
+        &ExprKind::MethodCall(ref name, _, ref args) if args.len() == 1 => {
+            let method = name.node.name.as_str().to_string();
+            match method.as_str(){
+                "clone" | "to_owned" | "to_vec" => Some((method.clone(),&args[0])),
+                _ => None,
+            }
+        },
+        _ => None,
+    }
+}
+
 // Get type of a filed of structure
 // If this variable cannot be found in current scope,
 // find it in outer scope
@@ -730,24 +1993,61 @@ fn get_var_type_struct(
     var_type
 }
 
-fn get_last_used_struct(structure:&HashMap<String,VarInfo>,
-                                        var: &Vec<String>) ->usize
+// Get the variable whose value is changed by an assignment to the given
+// expression, or read by reading it, for example: p for p.x, *p or p[0]
+fn assigned_root(ex: &Expr) -> Option<Vec<String>>{
+    match &ex.node{
+        &ExprKind::Path(_, ref p) if p.segments.len() == 1 =>
+            Some(vec![p.segments[0].identifier.name.as_str().to_string()]),
+        &ExprKind::Field(ref e, _) | &ExprKind::TupField(ref e, _) |
+        &ExprKind::Index(ref e, _) | &ExprKind::Paren(ref e) |
+        &ExprKind::Unary(ast::UnOp::Deref, ref e) => assigned_root(e),
+        _ => None,
+    }
+}
+
+// Get the variable a field holding a reference refers to
+fn get_ref_to_struct(
+                                    structure:&HashMap<String,VarInfo>,
+                                    var:&Vec<String>)->Option<Vec<String>>
 {
-    let mut last_used:usize = 0;
     if let Some(info) = structure.get(&var[0]){
         if var.len()==1{
-            if let Some(last_used_loc) = info.last_used_loc{
-                last_used = last_used_loc;   
-            }
-        }else{
-            let mut new_var = (*var).clone();
-            new_var.remove(0);
-            if let Some(ref structure) = info.structure{
-                last_used=get_last_used_struct(structure,&new_var);
-            }
+            return info.ref_to.clone()
+        }
+        let mut new_var = (*var).clone();
+        new_var.remove(0);
+        if let Some(ref structure) = info.structure{
+            return get_ref_to_struct(structure,&new_var)
+        }
+    }
+    None
+}
+
+// Set the drop points and the move state of a variable and of its fields
+fn set_dataflow(var: &Vec<String>,
+                        decl: usize,
+                        info: &mut VarInfo,
+                        drops: &HashMap<Place,Vec<usize>>,
+                        moves: &MoveAnalysis)
+{
+    let place = Place{ var: var.clone(), decl: decl };
+    info.drop_points = match drops.get(&place){
+        Some(points) => points.clone(),
+        None => vec![],
+    };
+    info.moved = match moves.states.get(&place){
+        Some(state) => *state,
+        None => MoveState::Init,
+    };
+    info.move_lines = moves.lines_moved(&place);
+    if let Some(ref mut structure) = info.structure{
+        for (field,field_info) in structure.iter_mut(){
+            let mut field_var = var.clone();
+            field_var.push(field.clone());
+            set_dataflow(&field_var,decl,field_info,drops,moves);
         }
     }
-    last_used
 }
 
 // check whether a field is exist
@@ -780,11 +2080,7 @@ fn record_last_used_for_struct(
 {
     if let Some(info) = structure.get_mut(&vars[0]){
         if vars.len() == 1{
-            if info.moved {
-                panic!("Try to move moved value !");
-            } else{
-                info.last_used_loc = Some(line);
-            }
+            info.last_used_loc = Some(line);
         }else{
             match &info.var_type{
                & Ty::NonPrimitive => {
@@ -801,34 +2097,6 @@ fn record_last_used_for_struct(
     }
 }
 
-// Change the moved state for a field
-fn move_ownership_for_struct(
-                                                    structure:&mut HashMap<String, VarInfo>,
-                                                    vars:&Vec<String>,
-                                                    )
-{
-    if let Some(info) = structure.get_mut(&vars[0]){
-        match  info.var_type {
-            Ty::NonPrimitive=>{
-                if vars.len()==1{
-                    if info.moved {
-                        panic!("Try to move moved value !");
-                    }else {
-                         info.moved = true;
-                    }
-                }else{
-                    let mut new_var = (*vars).clone();
-                    new_var.remove(0);
-                    if let Some(ref mut structure) = info.structure{
-                        move_ownership_for_struct(structure,&new_var);
-                    }
-                }
-            },
-            _=>(),
-        }
-    }
-}
-
 // Record a field is referred by another variable
 fn ref_to_var_struct(
                                 structure:&mut HashMap<String,VarInfo>,
@@ -856,10 +2124,10 @@ fn check_move_struct(
 {
     let mut is_move = false;
     if let Some(info) = structure.get(var){
-        if !info.moved{
+        if info.moved == MoveState::Init{
             if let Some(ref structure) = info.structure{
-                for(fields, _ ) in structure{
-                    is_move=is_move|check_move_struct(&fields,structure);
+                for fields in structure.keys(){
+                    is_move |= check_move_struct(fields,structure);
                 }
             }
         }else{
@@ -869,24 +2137,22 @@ fn check_move_struct(
     is_move
 }
 
-// Check whether a field is referred by another variables
+// Check whether a field, or one of its own fields, is not referred by
+// another variable
 fn no_var_ref_struct(var:&str,
                                 structure:& HashMap<String,VarInfo>
                                 )->bool
 {
     let mut is_reffered = false;
     if let Some(info) = structure.get(var){
-        if !info.ref_by.is_empty(){
-            if let Some(ref structure) = info.structure{
-                for(fields, _ ) in structure{
-                    is_reffered = is_reffered|no_var_ref_struct(&fields,structure);
-                }
+        is_reffered = !info.ref_by.is_empty();
+        if let Some(ref structure) = info.structure{
+            for fields in structure.keys(){
+                is_reffered |= !no_var_ref_struct(fields,structure);
             }
-        }else{
-            is_reffered = true;
         }
     }  
-    is_reffered
+    !is_reffered
 }
 
 // Change a field's variable information when there is a assignement for
@@ -895,18 +2161,17 @@ fn change_var_info_struct(var:&Vec<String>,
                                             structure:&mut HashMap<String,VarInfo>,
                                             info:VarInfo)
     {
-        if let Some(old_info) = structure.get_mut(&var[0]){
-            if var.len()==1{
-                   *old_info = info;
-            }else{                
-                let mut new_var = (*var).clone();
-                new_var.remove(0);
-                if let Some(ref mut structure) = old_info.structure{
-                    change_var_info_struct(&new_var,structure,info);
-                }
+        if var.len()==1{
+            structure.insert(var[0].clone(),info);
+        }else if let Some(old_info) = structure.get_mut(&var[0]){
+            let mut new_var = (*var).clone();
+            new_var.remove(0);
+            if old_info.structure.is_none(){
+                old_info.structure = Some(HashMap::new());
+            }
+            if let Some(ref mut structure) = old_info.structure{
+                change_var_info_struct(&new_var,structure,info);
             }
-        }else{
-            panic!{"Unresolved field!"};
         }
     }
 
@@ -919,7 +2184,7 @@ impl <'v,'a> Visitor <'v> for SymbolTable <'a> {
         self.get_in_scope(b);
         let len = self.enclose_scope.len();
        {
-            let ref mut cur_scope = self.enclose_scope[len-1];  
+            let cur_scope = &mut self.enclose_scope[len-1];  
             visit::walk_block((*cur_scope).deref_mut() ,b);
         }
     }
@@ -937,7 +2202,9 @@ impl <'v,'a> Visitor <'v> for SymbolTable <'a> {
                 let pattern =  &l.pat.node;    
                 // Deal with the right hand side of the let binding
                 match init.as_ref() {
-                    Some(ref expr) => { 
+                    Some(expr) => { 
+                        self.record_clone(expr,None);
+                        self.record_borrowed_move(expr);
                         let resolver = resolve::resolve_expr(expr, self.enum_list,self.fun_records,self,self.structure_list);
                         let line = self.location(expr.span);
                         self.change_and_used(&resolver,line);
@@ -948,6 +2215,21 @@ impl <'v,'a> Visitor <'v> for SymbolTable <'a> {
                                  self.local_lhs (pattern, var_type, None,resolver.structure);
                             }
                         }
+                        // The annotated type comes before the type of the value
+                        let (type_name, mut_ref, pointee) = match l.ty{
+                            Some(ref ty) => (self.type_name(ty), mut_ref_type(ty), pointee_type(ty,self.alias_list)),
+                            None => match expr.node{
+                                ExprKind::AddrOf(ast::Mutability::Mutable, _) => (resolver.type_name, true, None),
+                                _ => (resolver.type_name, false, None),
+                            },
+                        };
+                        self.set_type_info(pattern,type_name,mut_ref,pointee);
+                        self.record_mut_binding(pattern,expr,line);
+                        let binding = match pattern{
+                            &PatKind::Ident(_, ref span_ident, _) => self.place(&vec![span_ident.node.name.as_str().to_string()]),
+                            _ => None,
+                        };
+                        self.record_result_use(expr,binding);
                     },
                     // If there is nothing on the right hand side 
                     None =>(),
@@ -961,38 +2243,139 @@ impl <'v,'a> Visitor <'v> for SymbolTable <'a> {
     // Visit the expression of the AST
     fn visit_expr(&mut self, ex: &'v Expr){
         let line = self.codemap.lookup_char_pos(ex.span.hi).line;
+        let start = self.codemap.lookup_char_pos(ex.span.lo).line;
         match &ex.node{
             // When visit a match expression, check whether the ownership of variable 
-           // used match is passed 
-            &ExprKind::Match( ref expr, _)=>{
+           // used match is passed. Each arm is a branch of the control flow
+            &ExprKind::Match( ref expr, ref arms)=>{
                 let resolve = resolve::resolve_expr(expr,self.enum_list,self.fun_records,self,self.structure_list);
                 self.change_and_used(&resolve,line);
-                visit::walk_expr(self,ex);
+                self.visit_expr(expr);
+                self.cfg(|c| c.begin_branch(start,line));
+                for arm in arms{
+                    let arm_line = self.inner_line(arm.body.span);
+                    self.cfg(|c| c.branch_arm(arm_line));
+                    self.visit_arm_in_scope(arm,&resolve);
+                    self.cfg(|c| c.end_arm());
+                }
+                self.cfg(|c| c.end_branch(true));
+            },
+            // `if let` destructures like a match with one arm, the variables
+            // bound by the pattern are declared in the block
+            &ExprKind::IfLet(ref pat, ref expr, ref block, ref else_expr)=>{
+                let resolve = resolve::resolve_expr(expr,self.enum_list,self.fun_records,self,self.structure_list);
+                self.change_and_used(&resolve,line);
+                self.visit_expr(expr);
+                self.cfg(|c| c.begin_branch(start,line));
+                let block_line = self.inner_line(block.span);
+                self.cfg(|c| c.branch_arm(block_line));
+                self.visit_block_with_pattern(block,&pat.node,&resolve);
+                self.cfg(|c| c.end_arm());
+                if let &Some(ref else_expr) = else_expr{
+                    let else_line = self.inner_line(else_expr.span);
+                    self.cfg(|c| c.branch_arm(else_line));
+                    self.visit_expr(else_expr);
+                    self.cfg(|c| c.end_arm());
+                }
+                self.cfg(|c| c.end_branch(else_expr.is_some()));
             },
             // When the visited expr is a Methodcall, record when are the variables
             // used and moved.
             &ExprKind::MethodCall(ref func_name, _ , ref args) =>{
-                let function = func_name.node.name.as_str().to_string();
+                let method = func_name.node.name.as_str().to_string();
                 let mut record_call = false;
                 
                let caller_resolve = resolve::resolve_expr(&args[0],self.enum_list,self.fun_records,self,self.structure_list); 
+                // The method is looked up by the type of the receiver
+                let key = self.method_key(caller_resolve.type_name.as_ref(),&method);
+                let function = key.clone().unwrap_or_else(|| method.clone());
+                // A builtin method that takes its receiver by value moves it
+                if key.is_none(){
+                    if let Some(Mode::Value) = builtin::get_receiver_mode(self.signatures,&method,&Ty::NonPrimitive,caller_resolve.type_name.as_ref()){
+                        self.record_borrowed_move(&args[0]);
+                    }
+                }
+
+                // The receiver is used by the call. A builtin method that takes
+                // its receiver by value moves the receiver
+                let mut receiver_mut = false;
+                if let Some(ref receiver) = caller_resolve.var_name{
+                    let mut moved = false;
+                    if key.is_none(){
+                        let receiver_type = self.get_var_type(receiver);
+                        match builtin::get_receiver_mode(self.signatures,&method,&receiver_type,caller_resolve.type_name.as_ref()){
+                            Some(Mode::Value) => match receiver_type{
+                                Ty::NonPrimitive => moved = true,
+                                _=>(),
+                            },
+                            Some(Mode::MutRef) => receiver_mut = true,
+                            _=>(),
+                        }
+                    }
+                    if moved{
+                        self.move_var(receiver,line);
+                    }else{
+                        self.use_var(receiver,line);
+                    }
+                    // A method of the program taking `&mut self` changes the
+                    // receiver, and so may a method that is not known
+                    let fun_records = self.fun_records;
+                    let receiver_mode = match key{
+                        Some(ref key) => fun_records.get(key).and_then(|info| info.receiver.clone()),
+                        None => builtin::get_receiver_mode(self.signatures,&method,&self.get_var_type(receiver),caller_resolve.type_name.as_ref()),
+                    };
+                    match receiver_mode{
+                        Some(Mode::MutRef) | None => {
+                            self.effect(receiver,line,Effect::Mutated);
+                            self.record_field_write(&args[0],false);
+                        },
+                        _ => (),
+                    }
+                    // A method of the owning type, or of a trait implemented
+                    // for it, needs a borrowed receiver to keep its type
+                    if key.is_some() | builtin::owner_only_method(&method){
+                        self.effect(receiver,line,Effect::Concrete);
+                    }
+                }
                 
+                let caller = self.summary_builder().map(|builder| builder.name().to_string());
                 let mut call_info = CallInfo{
                     receiver: caller_resolve.var_name,                   
                     arguments: vec![],
                     call_location: line,
+                    point: None,
+                    places: vec![],
+                    live_after: vec![],
+                    cloned: vec![],
+                    caller: caller,
                 };
                 
-                if self.fun_records.contains_key(&function){
+                if key.is_some(){
                     record_call =true;
                 }
 
                 // When a method is called, check the type of each argument
+                let mut lent = vec![false];
                 let mut count = 1;
                 while count < args.len(){
                     let arg = &args[count]; 
+                    lent.push(self.lend_argument(arg,&function,count-1,line));
+                    self.record_clone(arg,Some(format!("passed to `{}`",method)));
+                    self.record_result_use(arg,None);
+                    let cloned = self.cloned_var(arg);
+                    call_info.cloned.push(cloned);
+                    self.record_argument_conversion(arg,&function,count-1);
+                    self.record_borrowed_move(arg);
                     let resolve = resolve::resolve_expr(arg,self.enum_list,self.fun_records,self,self.structure_list);
-                    self.change_and_used(&resolve,line);
+                    self.pass_argument(&resolve,&function,count-1,line);
+                    // A builtin method that changes its receiver keeps the
+                    // arguments it takes, for example: v.push(x)
+                    if receiver_mut{
+                        if let Some(ref var) = resolve.var_name{
+                            self.effect(var,line,Effect::Stored);
+                        }
+                    }
+                    call_info.places.push(self.argument_place(&resolve));
                     match resolve.var_name{
                         Some(arg_name)=> call_info.arguments.push(arg_name),
                         None =>{
@@ -1006,43 +2389,61 @@ impl <'v,'a> Visitor <'v> for SymbolTable <'a> {
                     count +=1;
                 } 
 
+                call_info.point = self.cfg_position();
                 if record_call{
-                    if self.call_records.contains_key(&function){
-                        if let Some(info) = self.call_records.get_mut(&function){
-                            info.push(call_info);
-                        }
-                    }else{
-                        self.call_records.insert(function,vec![call_info]);
-                    }
+                    self.call_records.entry(function).or_insert_with(Vec::new).push(call_info);
                 }
 
-                visit::walk_expr(self,ex);   
+                self.visit_arguments(args,&lent);
             },
             // When the visited expr is a Call, record when are the variables
             // used and moved.
             &ExprKind::Call(ref fun_name, ref args)=>{
                 let mut function = "".to_string();
+                let mut key = None;
                 match &fun_name.node{
-                    &ExprKind::Path(_, ref p) if p.segments.len() == 1 =>{
-                        function = p.segments[0].identifier.name.as_str().to_string();
+                    &ExprKind::Path(_, ref p) =>{
+                        let segments:Vec<String> = p.segments.iter().map(|seg| seg.identifier.name.as_str().to_string()).collect();
+                        key = self.function_key(&segments);
+                        function = segments.join("::");
                     },
                     _=>(), 
                 }
+                if let Some(ref key) = key{
+                    function = key.clone();
+                }
                 let mut record_call = false;
 
+                let caller = self.summary_builder().map(|builder| builder.name().to_string());
                 let mut call_info = CallInfo{
                     receiver: None,
                     arguments: vec![],
                     call_location: line,
+                    point: None,
+                    places: vec![],
+                    live_after: vec![],
+                    cloned: vec![],
+                    caller: caller,
                 };
                 
-                if self.fun_records.contains_key(&function){
+                if key.is_some(){
                     record_call =true;
                 }
 
+                let mut index = 0;
+                let mut lent = vec![];
                 for arg in args {
+                    lent.push(self.lend_argument(arg,&function,index,line));
+                    self.record_clone(arg,Some(format!("passed to `{}`",function)));
+                    self.record_result_use(arg,None);
+                    let cloned = self.cloned_var(arg);
+                    call_info.cloned.push(cloned);
+                    self.record_argument_conversion(arg,&function,index);
+                    self.record_borrowed_move(arg);
                     let resolve = resolve::resolve_expr(arg,self.enum_list,self.fun_records,self,self.structure_list);
-                    self.change_and_used(&resolve,line);
+                    self.pass_argument(&resolve,&function,index,line);
+                    index+=1;
+                    call_info.places.push(self.argument_place(&resolve));
 
                     match resolve.var_name{
                         Some(arg_name)=> call_info.arguments.push(arg_name),
@@ -1055,43 +2456,117 @@ impl <'v,'a> Visitor <'v> for SymbolTable <'a> {
                     }
                 }
               
+               call_info.point = self.cfg_position();
                if record_call{
-                    if self.call_records.contains_key(&function){
-                        if let Some(info) = self.call_records.get_mut(&function){
-                            info.push(call_info);
-                        }
-                    }else{
-                        self.call_records.insert(function,vec![call_info]);
-                    }
+                    self.call_records.entry(function).or_insert_with(Vec::new).push(call_info);
                 }
 
-                visit::walk_expr(self,ex);                            
+                self.visit_expr(fun_name);
+                self.visit_arguments(args,&lent);
+            },
+            // The blocks of an if expression are branches of the control
+            // flow. The variables declared outside of it that are used inside
+            // are used until the last line of the expression
+            &ExprKind::If (ref cond, ref block, ref else_expr)=>{
+                let cond_line = self.codemap.lookup_char_pos(cond.span.hi).line;
+                self.read_place(cond,cond_line);
+                self.visit_expr(cond);
+                self.cfg(|c| c.begin_branch(start,line));
+                let block_line = self.inner_line(block.span);
+                self.cfg(|c| c.branch_arm(block_line));
+                self.visit_block(block);
+                self.cfg(|c| c.end_arm());
+                if let &Some(ref else_expr) = else_expr{
+                    let else_line = self.inner_line(else_expr.span);
+                    self.cfg(|c| c.branch_arm(else_line));
+                    self.visit_expr(else_expr);
+                    self.cfg(|c| c.end_arm());
+                }
+                self.cfg(|c| c.end_branch(else_expr.is_some()));
+            },
+            // The condition of a while loop is evaluated before each iteration
+            &ExprKind::While(ref cond, ref block, ref label)=>{
+                let label = label.map(|l| l.node.name.as_str().to_string());
+                self.cfg(|c| c.begin_loop(start,line,label));
+                let cond_line = self.codemap.lookup_char_pos(cond.span.hi).line;
+                self.read_place(cond,cond_line);
+                self.visit_expr(cond);
+                let body_line = self.inner_line(block.span);
+                self.cfg(|c| c.loop_body(body_line));
+                self.visit_block(block);
+                self.cfg(|c| c.end_loop());
+            },
+            &ExprKind::WhileLet(ref pat, ref expr, ref block, ref label)=>{
+                let label = label.map(|l| l.node.name.as_str().to_string());
+                self.cfg(|c| c.begin_loop(start,line,label));
+                let resolve = resolve::resolve_expr(expr,self.enum_list,self.fun_records,self,self.structure_list);
+                self.change_and_used(&resolve,line);
+                self.visit_expr(expr);
+                let body_line = self.inner_line(block.span);
+                self.cfg(|c| c.loop_body(body_line));
+                self.visit_block_with_pattern(block,&pat.node,&resolve);
+                self.cfg(|c| c.end_loop());
             },
-            // When visitor meet If or while expression
-            // Set the last used location of all the variables that are used 
-            // inside this expression to the last line of this expression
-            &ExprKind::If (_, _, _) | &ExprKind::While(_,_,_)=>{
-                let start =  self.codemap.lookup_char_pos(ex.span.lo).line;
-                let end = self.codemap.lookup_char_pos(ex.span.hi).line;
+            // The iterator of a for loop is evaluated once, before the loop
+            &ExprKind::ForLoop(_, ref iter, ref block, ref label)=>{
+                let iter_resolve = resolve::resolve_expr(iter,self.enum_list,self.fun_records,self,self.structure_list);
+                self.change_and_used(&iter_resolve,start);
+                self.visit_expr(iter);
+                let label = label.map(|l| l.node.name.as_str().to_string());
+                self.cfg(|c| c.begin_loop(start,line,label));
+                let body_line = self.inner_line(block.span);
+                self.cfg(|c| c.loop_body(body_line));
+                self.visit_block(block);
+                self.cfg(|c| c.end_loop());
+            },
+            &ExprKind::Loop(ref block, ref label)=>{
+                let label = label.map(|l| l.node.name.as_str().to_string());
+                self.cfg(|c| c.begin_loop(start,line,label));
+                self.visit_block(block);
+                self.cfg(|c| c.end_loop());
+            },
+            // Early exits of a function or a loop
+            &ExprKind::Ret(ref value)=>{
+                if let &Some(ref value) = value{
+                    self.return_expr(value);
+                }
                 visit::walk_expr(self,ex);
-                self.record_last_used_for_if(start,end,2);
-                self.record_call_loc_for_if(start,end,2);
-
+                self.cfg(|c| c.jump_exit());
             },
-            &ExprKind::Loop(_,_)=>{
-                let start =  self.codemap.lookup_char_pos(ex.span.lo).line;
-                let end = self.codemap.lookup_char_pos(ex.span.hi).line;
+            &ExprKind::Break(ref label, _)=>{
                 visit::walk_expr(self,ex);
-                self.record_call_loc_for_if(start,end,1);
-                self.record_last_used_for_if(start,end,1);
-            }
+                let label = label.map(|l| l.node.name.as_str().to_string());
+                self.cfg(|c| c.jump_loop(label,false));
+            },
+            &ExprKind::Continue(ref label)=>{
+                let label = label.map(|l| l.node.name.as_str().to_string());
+                self.cfg(|c| c.jump_loop(label,true));
+            },
+            &ExprKind::Try(_)=>{
+                visit::walk_expr(self,ex);
+                self.cfg(|c| c.may_exit());
+            },
             // When visitor meet assign, check whether the name is exist.
             &ExprKind::Assign(ref lvalue,ref rvalue)=>{
                 let lvalue_resolve = resolve::resolve_expr(lvalue,self.enum_list, self.fun_records, self,self.structure_list);
                 let rvalue_resolve = resolve::resolve_expr(rvalue,self.enum_list,self.fun_records,self,self.structure_list);
                
+                self.record_clone(rvalue,None);
+                self.record_borrowed_move(rvalue);
+                self.record_result_use(rvalue,None);
                 self.change_and_used(&rvalue_resolve,line);
 
+                // A value assigned to a field or through a reference is kept
+                // by the variable assigned to, which is changed
+                self.record_field_write(lvalue,false);
+                if lvalue_resolve.var_name.as_ref().map_or(true, |var| var.len() > 1){
+                    if let Some(root) = assigned_root(lvalue){
+                        self.effect(&root,line,Effect::Mutated);
+                        if let Some(ref var) = rvalue_resolve.var_name{
+                            self.effect(var,line,Effect::Stored);
+                        }
+                    }
+                }
                 if let Some(ref var) = lvalue_resolve.var_name{
                     if let Some(var_type) = rvalue_resolve.var_type{
                         if let Some(ref_to) = rvalue_resolve.ref_to{
@@ -1105,8 +2580,52 @@ impl <'v,'a> Visitor <'v> for SymbolTable <'a> {
                 }
                 visit::walk_expr(self,ex);
             }
+            // A compound assignment reads and changes the variable assigned to
+            &ExprKind::AssignOp(_, ref lvalue, ref rvalue)=>{
+                let rvalue_resolve = resolve::resolve_expr(rvalue,self.enum_list,self.fun_records,self,self.structure_list);
+                self.change_and_used(&rvalue_resolve,line);
+                self.record_field_write(lvalue,false);
+                if let Some(root) = assigned_root(lvalue){
+                    self.use_var(&root,line);
+                    self.effect(&root,line,Effect::Mutated);
+                }
+                visit::walk_expr(self,ex);
+            },
+            // A mutable borrow may change the variable borrowed
+            &ExprKind::AddrOf(ast::Mutability::Mutable, ref e)=>{
+                self.record_field_write(e,false);
+                if let Some(root) = assigned_root(e){
+                    self.effect(&root,line,Effect::Mutated);
+                }
+                visit::walk_expr(self,ex);
+            },
+            // The values of the fields of a structure literal are kept by
+            // the structure
+            &ExprKind::Struct(ref path, ref fields, _)=>{
+                self.record_field_inits(path,fields);
+                for field in fields{
+                    self.record_clone(&field.expr,Some(format!("stored in field `{}`",field.ident.node.name.as_str())));
+                    self.record_borrowed_move(&field.expr);
+                    self.record_result_use(&field.expr,None);
+                    let resolve = resolve::resolve_expr(&field.expr,self.enum_list,self.fun_records,self,self.structure_list);
+                    if let Some(ref var) = resolve.var_name{
+                        self.effect(var,line,Effect::Stored);
+                    }
+                }
+                visit::walk_expr(self,ex);
+            },
             &ExprKind::Binary(ref binop,ref first, ref second)=>{
-                if !binop.node.is_comparison(){
+                // A string compares equal to its borrowed form
+                if (binop.node == ast::BinOpKind::Eq) | (binop.node == ast::BinOpKind::Ne){
+                    let reason = format!("compared with `{}`",binop.node.to_string());
+                    self.record_conversion(first,false,reason.clone(),None);
+                    self.record_conversion(second,false,reason,None);
+                }
+                // A comparison borrows its operands
+                if binop.node.is_comparison(){
+                    self.read_place(first,line);
+                    self.read_place(second,line);
+                }else{
                     let first_resolve = resolve::resolve_expr(first,
                                                                                         self.enum_list,
                                                                                         self.fun_records,
@@ -1120,14 +2639,85 @@ impl <'v,'a> Visitor <'v> for SymbolTable <'a> {
 
                     self.change_and_used(&first_resolve,line);
                     self.change_and_used(&second_resolve,line);
-                
+                    // A value read through a reference, for example: *r
+                    if first_resolve.var_name.is_none(){
+                        self.read_place(first,line);
+                    }
+                    if second_resolve.var_name.is_none(){
+                        self.read_place(second,line);
+                    }
                 }
+                // Calls in the operands use their arguments like any other
+                // call, for example: v.len() > 8
+                visit::walk_expr(self,ex);
             }
             _=>visit::walk_expr(self,ex),
         }     
     }
 
 
+    // Remember the modules, impl blocks and traits the functions are
+    // defined in, so that they get the same keys as in the pre-record
+    fn visit_item(&mut self, item: &'v ast::Item){
+        match &item.node{
+            &ast::ItemKind::Impl(_,_,_,ref trait_ref,ref self_ty,_) => {
+                let (ty, prefix) = pre_record::impl_prefix(self_ty,trait_ref,self.alias_list,&self.mod_path,self.type_keys);
+                let outer_ty = mem::replace(&mut self.self_type,ty);
+                let outer_prefix = mem::replace(&mut self.fn_prefix,prefix);
+                visit::walk_item(self,item);
+                self.self_type = outer_ty;
+                self.fn_prefix = outer_prefix;
+            },
+            &ast::ItemKind::Trait(..) => {
+                let outer_prefix = mem::replace(&mut self.fn_prefix,Some(item.ident.name.as_str().to_string()));
+                visit::walk_item(self,item);
+                self.fn_prefix = outer_prefix;
+            },
+            &ast::ItemKind::Mod(_) => {
+                self.mod_path.push(item.ident.name.as_str().to_string());
+                visit::walk_item(self,item);
+                self.mod_path.pop();
+            },
+            _ => visit::walk_item(self,item),
+        }
+    }
+
+    // Lower the body of each function into its own control flow graph,
+    // which is kept by the scope the function is defined in
+    fn visit_fn(&mut self, fk: FnKind<'v>, fd: &'v FnDecl, s: Span, _: NodeId){
+        // Functions are keyed by their path, for example: util::parse,
+        // Point::new or <Point as Display>::fmt
+        let name = match fk{
+            FnKind::ItemFn(ident, ..) => pre_record::qualify(&self.mod_path,&ident.name.as_str()),
+            FnKind::Method(ident, ..) => match self.fn_prefix{
+                Some(ref prefix) => prefix.clone() + "::" + &ident.name.as_str(),
+                None => ident.name.as_str().to_string(),
+            },
+            // A closure is lowered into the graph of the enclosing function
+            FnKind::Closure(_) => return visit::walk_fn(self,fk,fd,s),
+        };
+        let outer = mem::replace(&mut self.cfg_builder, Some(CfgBuilder::new(name.clone())));
+        let outer_summary = mem::replace(&mut self.summary_builder, Some(SummaryBuilder::new(name.clone())));
+        match fk{
+            FnKind::ItemFn(.., body) | FnKind::Method(.., body) => self.visit_fn_body(&name,fd,body),
+            _ => visit::walk_fn(self,fk,fd,s),
+        }
+        if let Some(summary) = mem::replace(&mut self.summary_builder, outer_summary){
+            self.summaries.push(summary);
+        }
+        if let Some(builder) = mem::replace(&mut self.cfg_builder, outer){
+            let cfg = builder.finish();
+            let live = liveness::analyze(&cfg);
+            let drops = live.drop_points(&cfg);
+            let moves = moves::analyze(&cfg);
+            if let Some(body) = self.enclose_scope.last_mut(){
+                body.apply_dataflow(&cfg,&live,&drops,&moves);
+            }
+            self.cfgs.push(cfg);
+            self.move_analyses.push(moves);
+        }
+    }
+
     // When a macro is meet, check which variable is used in this macro
     fn visit_mac (&mut self, _mac: &'v Mac) {
         let mut idents = vec![];
@@ -1143,12 +2733,22 @@ impl <'v,'a> Visitor <'v> for SymbolTable <'a> {
                 _=>(),
             }
         }
+        // write! and writeln! change the value they write to
+        let name = _mac.node.path.segments.last().map(|segment| segment.identifier.name.as_str().to_string());
+        if (name == Some("write".to_string())) | (name == Some("writeln".to_string())){
+            if let Some(ident) = idents.first(){
+                let var = vec![ident.name.as_str().to_string()];
+                let line = self.location(_mac.span);
+                self.effect(&var,line,Effect::Mutated);
+            }
+        }
         for ident in idents{
             //??????
             let var = vec![ident.name.as_str().to_string()];
             let line = self.location(_mac.span);
-            self.record_last_used(&var, line)
+            self.use_var(&var, line)
         }
+        self.record_formatted_conversions(_mac);
         visit::walk_mac (self, _mac)
     }
 }
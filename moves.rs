@@ -0,0 +1,393 @@
+ // File: The move analysis of the tool
+ // Purpose: Functions defined in this file follow, over the control flow
+ //                 graph of a function, whether the value of each place is
+ //                 initialised, moved or moved on only some of the paths
+ //                 that reach a program point
+ // Author : Ziling Zhou (802414)
+
+use std::collections::HashMap;
+
+use cfg::{Cfg,Action,Place,Region,RegionKind,EXIT};
+
+// The state of a place at a program point. A place that is not in the
+// state of a block is not declared yet on any path
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum MoveState{
+    Init,
+    // Moved on some of the paths only
+    MaybeMoved,
+    Moved,
+}
+
+// Merge the states of a place coming from two paths
+fn join(a:MoveState, b:MoveState) -> MoveState{
+    if a == b { a } else { MoveState::MaybeMoved }
+}
+
+// A use or a move of a value that is moved on some or all of the paths
+#[derive(Debug)]
+pub struct MovedUse{
+    pub place: Place,
+    pub line: usize,
+    pub maybe: bool,
+    pub move_lines: Vec<usize>,
+}
+
+// A use of a value as a whole after some of its fields are moved out
+#[derive(Debug)]
+pub struct PartialUse{
+    pub place: Place,
+    pub line: usize,
+    // The fields that are moved or may be moved before the use
+    pub fields: Vec<Place>,
+    pub move_lines: Vec<usize>,
+}
+
+// A value that is moved on some of the paths into a join only
+#[derive(Debug)]
+pub struct ConditionalMove{
+    pub place: Place,
+    // Line before which the paths join
+    pub join_line: usize,
+    pub move_lines: Vec<usize>,
+}
+
+// A value moved inside a loop that is used again on the next iteration
+#[derive(Debug)]
+pub struct LoopMove{
+    pub place: Place,
+    // Lines of the loop
+    pub start: usize,
+    pub end: usize,
+    // Lines the value is moved on inside the loop
+    pub move_lines: Vec<usize>,
+    // Line the value is used on again
+    pub line: usize,
+    // Moved on some of the iterations only
+    pub maybe: bool,
+}
+
+// The result of the move analysis of a function
+#[derive(Debug)]
+pub struct MoveAnalysis{
+    pub name: String,
+    // The state of each place at the end of the function
+    pub states: HashMap<Place,MoveState>,
+    pub moved_uses: Vec<MovedUse>,
+    pub partial_uses: Vec<PartialUse>,
+    pub conditional: Vec<ConditionalMove>,
+    pub loop_moves: Vec<LoopMove>,
+    // Values that may or may not be moved when they go out of scope,
+    // Rust keeps a drop flag for them at run time
+    pub drop_flags: Vec<Place>,
+    // The lines each place is moved on
+    move_lines: HashMap<Place,Vec<usize>>,
+}
+
+impl MoveAnalysis{
+    // The lines a place, its container or one of its parts is moved on
+    pub fn lines_moved(&self, place:&Place) -> Vec<usize>{
+        moved_lines(place,&self.move_lines)
+    }
+}
+
+// Whether the place is `part` or a part of it
+fn contains(whole:&Place, part:&Place) -> bool{
+    (whole.decl == part.decl) && (whole.var.len() <= part.var.len())
+        && (whole.var[..] == part.var[..whole.var.len()])
+}
+
+// Merge the states at the end of the given blocks
+fn merge(outs:&[&HashMap<Place,MoveState>]) -> HashMap<Place,MoveState>{
+    let mut state:HashMap<Place,MoveState> = HashMap::new();
+    for out in outs{
+        for (place,s) in out.iter(){
+            let merged = match state.get(place){
+                Some(old) => join(*old,*s),
+                None => *s,
+            };
+            state.insert(place.clone(),merged);
+        }
+    }
+    state
+}
+
+// The state at a point, given the state before it
+fn transfer(places:&[Place], action:&Action, at:&Place, state:&mut HashMap<Place,MoveState>){
+    let new_state = match action{
+        &Action::Def => MoveState::Init,
+        &Action::Move => MoveState::Moved,
+        &Action::Use => return,
+    };
+    for place in places{
+        if contains(at,place){
+            state.insert(place.clone(),new_state);
+        }
+    }
+}
+
+// The state of a place, a place is moved if its container is
+fn state_of(place:&Place, state:&HashMap<Place,MoveState>) -> MoveState{
+    let mut result = MoveState::Init;
+    for (other,s) in state{
+        if contains(other,place) & (*s != MoveState::Init) &
+            ((*s == MoveState::Moved) | (result == MoveState::Init)){
+            result = *s;
+        }
+    }
+    result
+}
+
+// The parts of a place that are moved or may be moved
+fn moved_fields(place:&Place, state:&HashMap<Place,MoveState>) -> Vec<Place>{
+    let mut fields:Vec<Place> = state.iter()
+                    .filter(|&(other,s)| (*s != MoveState::Init) & (other != place) & contains(place,other))
+                    .map(|(other,_)| other.clone()).collect();
+    fields.sort_by(|a,b| a.var.cmp(&b.var));
+    // Keep the outermost moved parts only, p.x covers p.x.y
+    fields.iter()
+        .filter(|field| !fields.iter().any(|other| (other != *field) & contains(other,field)))
+        .cloned().collect()
+}
+
+// Entry point of this file
+// Follow the move state of every place through the graph of a function
+pub fn analyze(cfg:&Cfg) -> MoveAnalysis{
+    let mut places = vec![];
+    let mut move_lines:HashMap<Place,Vec<usize>> = HashMap::new();
+    for block in &cfg.blocks{
+        for point in &block.points{
+            if !places.contains(&point.place){
+                places.push(point.place.clone());
+            }
+            if point.action == Action::Move{
+                move_lines.entry(point.place.clone()).or_insert_with(Vec::new).push(point.line);
+            }
+        }
+    }
+
+    // Iterate until nothing changes, a block starts with the merged
+    // states at the end of its predecessors
+    let mut outs:Vec<HashMap<Place,MoveState>> = vec![HashMap::new(); cfg.blocks.len()];
+    let mut changed = true;
+    while changed{
+        changed = false;
+        for (index,block) in cfg.blocks.iter().enumerate(){
+            let mut state = {
+                let pred_outs:Vec<&HashMap<Place,MoveState>> = block.preds.iter().map(|p| &outs[*p]).collect();
+                merge(&pred_outs)
+            };
+            for point in &block.points{
+                transfer(&places,&point.action,&point.place,&mut state);
+            }
+            if state != outs[index]{
+                outs[index] = state;
+                changed = true;
+            }
+        }
+    }
+
+    let mut analysis = MoveAnalysis{
+        name: cfg.name.clone(),
+        states: HashMap::new(),
+        moved_uses: vec![],
+        partial_uses: vec![],
+        conditional: vec![],
+        loop_moves: vec![],
+        drop_flags: vec![],
+        move_lines: HashMap::new(),
+    };
+    for (index,block) in cfg.blocks.iter().enumerate(){
+        let pred_outs:Vec<&HashMap<Place,MoveState>> = block.preds.iter().map(|p| &outs[*p]).collect();
+        let mut state = merge(&pred_outs);
+
+        // Paths that join with a value moved on some of them only
+        if pred_outs.len() > 1{
+            for (place,s) in &state{
+                let moved_on_some = pred_outs.iter().any(|out| match out.get(place){
+                    Some(&MoveState::Init) | None => false,
+                    _ => true,
+                });
+                if (*s == MoveState::MaybeMoved) & moved_on_some
+                    & !analysis.conditional.iter().any(|c| c.place == *place){
+                    analysis.conditional.push(ConditionalMove{
+                        place: place.clone(),
+                        join_line: block.line,
+                        move_lines: moved_lines(place,&move_lines),
+                    });
+                }
+            }
+        }
+
+        for point in &block.points{
+            if point.action != Action::Def{
+                let s = state_of(&point.place,&state);
+                if s != MoveState::Init{
+                    analysis.moved_uses.push(MovedUse{
+                        place: point.place.clone(),
+                        line: point.line,
+                        maybe: s == MoveState::MaybeMoved,
+                        move_lines: moved_lines(&point.place,&move_lines),
+                    });
+                }else{
+                    let fields = moved_fields(&point.place,&state);
+                    if !fields.is_empty(){
+                        let mut lines = vec![];
+                        for field in &fields{
+                            lines.extend(moved_lines(field,&move_lines));
+                        }
+                        lines.sort();
+                        lines.dedup();
+                        analysis.partial_uses.push(PartialUse{
+                            place: point.place.clone(),
+                            line: point.line,
+                            fields: fields,
+                            move_lines: lines,
+                        });
+                    }
+                }
+            }
+            transfer(&places,&point.action,&point.place,&mut state);
+        }
+        if index == EXIT{
+            for (place,s) in &state{
+                if *s == MoveState::MaybeMoved{
+                    analysis.drop_flags.push(place.clone());
+                }
+            }
+            analysis.states = state;
+        }
+    }
+    analysis.drop_flags.sort_by(|a,b| a.var.cmp(&b.var));
+    for region in &cfg.regions{
+        if region.kind == RegionKind::Loop{
+            loop_carried(cfg,&places,&outs,region,&mut analysis.loop_moves);
+        }
+    }
+    analysis.move_lines = move_lines;
+    analysis
+}
+
+// Merge the states at the end of the given blocks, a place that is not in
+// the state of a block is not moved there
+fn merge_carried(outs:&[&HashMap<Place,MoveState>]) -> HashMap<Place,MoveState>{
+    let mut state:HashMap<Place,MoveState> = HashMap::new();
+    for out in outs{
+        for place in out.keys(){
+            let mut merged = None;
+            for other in outs{
+                let s = match other.get(place){
+                    Some(s) => *s,
+                    None => MoveState::Init,
+                };
+                merged = match merged{
+                    Some(m) => Some(join(m,s)),
+                    None => Some(s),
+                };
+            }
+            if let Some(merged) = merged{
+                state.insert(place.clone(),merged);
+            }
+        }
+    }
+    state
+}
+
+// Find the values that are moved in the body of a loop and used again on
+// the next iteration before they get a new value. The states that come
+// back to the head of the loop are followed through the body once more
+fn loop_carried(cfg:&Cfg,
+                    places:&[Place],
+                    outs:&[HashMap<Place,MoveState>],
+                    region:&Region,
+                    loop_moves:&mut Vec<LoopMove>)
+{
+    let back:Vec<&HashMap<Place,MoveState>> = cfg.blocks[region.head].preds.iter()
+                    .filter(|p| region.blocks.contains(p)).map(|p| &outs[*p]).collect();
+    let mut start = merge_carried(&back);
+    start.retain(|_,s| *s != MoveState::Init);
+    if start.is_empty(){
+        return
+    }
+
+    let mut carried:HashMap<usize,HashMap<Place,MoveState>> = HashMap::new();
+    let mut changed = true;
+    while changed{
+        changed = false;
+        for block in &region.blocks{
+            let mut state = carried_in(cfg,region,&start,&carried,*block);
+            for point in &cfg.blocks[*block].points{
+                transfer(places,&point.action,&point.place,&mut state);
+            }
+            if carried.get(block) != Some(&state){
+                carried.insert(*block,state);
+                changed = true;
+            }
+        }
+    }
+
+    let moves = region_moves(cfg,region);
+    for block in &region.blocks{
+        let mut state = carried_in(cfg,region,&start,&carried,*block);
+        for point in &cfg.blocks[*block].points{
+            if point.action != Action::Def{
+                let s = state_of(&point.place,&state);
+                let reported = loop_moves.iter().any(|m| m.place == point.place);
+                if (s != MoveState::Init) & !reported{
+                    loop_moves.push(LoopMove{
+                        place: point.place.clone(),
+                        start: region.start,
+                        end: region.end,
+                        move_lines: moved_lines(&point.place,&moves),
+                        line: point.line,
+                        maybe: s == MoveState::MaybeMoved,
+                    });
+                }
+            }
+            transfer(places,&point.action,&point.place,&mut state);
+        }
+    }
+}
+
+// The state at the start of a block of a loop on the next iteration
+fn carried_in(cfg:&Cfg,
+                region:&Region,
+                start:&HashMap<Place,MoveState>,
+                carried:&HashMap<usize,HashMap<Place,MoveState>>,
+                block:usize) -> HashMap<Place,MoveState>
+{
+    if block == region.head{
+        return start.clone()
+    }
+    let empty = HashMap::new();
+    let pred_outs:Vec<&HashMap<Place,MoveState>> = cfg.blocks[block].preds.iter()
+                    .filter(|p| region.blocks.contains(p))
+                    .map(|p| carried.get(p).unwrap_or(&empty)).collect();
+    merge_carried(&pred_outs)
+}
+
+// The lines each place is moved on inside a region
+fn region_moves(cfg:&Cfg, region:&Region) -> HashMap<Place,Vec<usize>>{
+    let mut moves:HashMap<Place,Vec<usize>> = HashMap::new();
+    for block in &region.blocks{
+        for point in &cfg.blocks[*block].points{
+            if point.action == Action::Move{
+                moves.entry(point.place.clone()).or_insert_with(Vec::new).push(point.line);
+            }
+        }
+    }
+    moves
+}
+
+// The lines a place, its container or one of its parts is moved on
+fn moved_lines(place:&Place, move_lines:&HashMap<Place,Vec<usize>>) -> Vec<usize>{
+    let mut lines = vec![];
+    for (moved,at) in move_lines{
+        if contains(moved,place) | contains(place,moved){
+            lines.extend(at.iter().cloned());
+        }
+    }
+    lines.sort();
+    lines.dedup();
+    lines
+}
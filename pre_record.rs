@@ -5,27 +5,297 @@
 // Author : Ziling Zhou (802414)
 
 
-use std::collections::HashMap;
+use std::collections::{HashMap,HashSet};
+use std::mem;
 
 use syntax::ast;
-use syntax::ast::{VariantData,NodeId,Item,ItemKind,FunctionRetTy,Mac,TyKind,FnDecl};
+use syntax::ast::{VariantData,NodeId,Item,ItemKind,FunctionRetTy,Mac,TyKind,FnDecl,ViewPath_};
+use syntax::ast::{Generics,WherePredicate,TyParamBound,PatKind};
 use syntax::codemap::Span;
+use syntax::print::pprust;
 use syntax::visit::{self,Visitor,FnKind};
 
-use builtin::Ty;
+use builtin::{self,Ty,Mode};
 use syntax::ptr::P;
 
 #[derive(Debug)]
 pub struct  FnInfo{
 	pub output: Option<Ty>,
 	pub input:usize,
+	// Positional fields of the return type when the function returns a tuple
+	pub output_fields: HashMap<String,Ty>,
+	// Parameters of the function, `self` is not included
+	pub params: Vec<ParamInfo>,
+	// Generic type parameters of the function (and of its impl block),
+	// with the traits they are bounded by
+	pub generics: HashMap<String,Vec<String>>,
+	// Name of the returned type, `Self` is replaced by the type of the
+	// impl block
+	pub output_name: Option<String>,
+	// The type the returned value can be borrowed as instead, for
+	// example: &str for String
+	pub borrowed_output: Option<String>,
+	// The returned type and its estimated size in bytes, when the value
+	// is returned by value
+	pub output_size: Option<(String,usize)>,
+	// Type of the impl block for a method, None for a function
+	pub self_ty: Option<String>,
+	// How a method takes `self`, None for a function
+	pub receiver: Option<Mode>,
+}
 
+// Information of a parameter of a function
+#[derive(Debug)]
+pub struct ParamInfo{
+	pub name: String,
+	pub ty: Ty,
+	// Name of the generic type parameter if the parameter is declared as one
+	pub generic: Option<String>,
+	// The declared type and a more general type that can be borrowed
+	// instead, for example: (&String, &str)
+	pub general: Option<(String,String)>,
+	// For a parameter declared as &mut T, the shared reference &T
+	pub shared: Option<String>,
+	// The type a parameter taken by value can be borrowed as instead,
+	// for example: &str for String
+	pub borrowed: Option<String>,
+	// The declared type and its estimated size in bytes, for a parameter
+	// taken by value
+	pub value_size: Option<(String,usize)>,
 }
+
+// What a type alias or an imported name stands for
+#[derive(Debug)]
+pub enum Alias{
+	// Another name, for example: `use std::string::String as Text;` 
+	// or `type Id = u64;`
+	Name(String),
+	// A type that is not a plain path, for example: `type Pair = (String, i32);`
+	Type(P<ast::Ty>),
+}
+
+// Types of the parameters taken by value and of the value returned by
+// value of a function, and the modules the function is defined in
+type FnTypes = (Vec<Option<P<ast::Ty>>>,Option<P<ast::Ty>>,Vec<String>);
+
 // Structure that used to store information that are recorded
 pub struct PreRecord{
 	pub fun_record: HashMap<String, FnInfo>,
-	pub enum_record: HashMap<String,Vec<String>>,
-	pub struct_record: HashMap<String,HashMap<String,Ty>>
+	// Enum name -> variant name -> payload fields of the variant.
+	// Fields of a tuple variant are keyed by their position
+	// Structures and enums are keyed by the path of their module, for
+	// example: shapes::Point
+	pub enum_record: HashMap<String,HashMap<String,HashMap<String,Ty>>>,
+	pub struct_record: HashMap<String,HashMap<String,Ty>>,
+	// Names of the types of the fields of each structure that own a value
+	// which is not copied
+	pub field_type_record: HashMap<String,HashMap<String,String>>,
+	// The other form of each field of each structure: the type an owned
+	// field can borrow, for example: str for String, and the type a
+	// reference field can own, for example: String for &'a str
+	pub field_form_record: HashMap<String,HashMap<String,String>>,
+	pub alias_record: HashMap<String,Alias>,
+	// Structures and enums that derive or implement Default
+	pub default_record: HashSet<String>,
+	// Keys of all the structures and enums of the program
+	pub type_keys: HashSet<String>,
+	// Types of the fields of each variant of each structure and enum, a
+	// structure has one variant
+	type_decls: HashMap<String,Vec<Vec<P<ast::Ty>>>>,
+	// Types of the parameters taken by value and of the value returned by
+	// value of each function
+	fn_types: HashMap<String,FnTypes>,
+	// Generic parameters of the impl block that is being visited
+	impl_generics: HashMap<String,Vec<String>>,
+	// Modules around the item that is being visited
+	mod_path: Vec<String>,
+	// Type and key prefix of the impl block or trait that is being visited
+	self_ty: Option<String>,
+	fn_prefix: Option<String>,
+}
+
+// Structure that used to store the type aliases and `use` declarations,
+// and the keys of the structures and enums. They are recorded before
+// everything else since a type can be used before it is declared
+struct AliasRecord{
+	alias_record: HashMap<String,Alias>,
+	type_keys: HashSet<String>,
+	mod_path: Vec<String>,
+}
+
+impl <'v> Visitor<'v> for AliasRecord {
+
+	fn visit_item(&mut self, item: &'v Item){
+		match &item.node {
+			// Type alias, for example: type Id = u64;
+			& ItemKind::Ty(ref ty,_)=>{
+				let alias = item.ident.name.as_str().to_string();
+				match &ty.node{
+					&TyKind::Path(_, ref p) =>{
+						let target = p.segments[p.segments.len()-1].identifier.name.as_str().to_string();
+						self.insert_alias(alias,target);
+					},
+					_=>{
+						self.alias_record.insert(alias,Alias::Type(ty.clone()));
+					},
+				}
+			},
+			// Use declaration, for example: use std::string::String as Text;
+			& ItemKind::Use(ref view_path)=>{
+				match &view_path.node{
+					&ViewPath_::ViewPathSimple(ref ident, ref p) =>{
+						let target = p.segments[p.segments.len()-1].identifier.name.as_str().to_string();
+						self.insert_alias(ident.name.as_str().to_string(),target);
+					},
+					&ViewPath_::ViewPathList(ref p, ref items) =>{
+						for item in items{
+							let mut target = item.node.name.name.as_str().to_string();
+							// `use a::b::{self}` imports `b` 
+							if String::eq(&target,"self") & !p.segments.is_empty(){
+								target = p.segments[p.segments.len()-1].identifier.name.as_str().to_string();
+							}
+							let mut alias = target.clone();
+							if let Some(ref rename) = item.node.rename{
+								alias = rename.name.as_str().to_string();
+							}
+							self.insert_alias(alias,target);
+						}
+					},
+					// Glob imports do not rename anything
+					_=>(),
+				}
+			},
+			& ItemKind::Enum(..) | & ItemKind::Struct(..)=>{
+				self.type_keys.insert(qualify(&self.mod_path,&item.ident.name.as_str()));
+			},
+			& ItemKind::Mod(_)=>{
+				self.mod_path.push(item.ident.name.as_str().to_string());
+				visit::walk_item(self,item);
+				self.mod_path.pop();
+				return
+			},
+			_=>(),
+		}
+		visit::walk_item(self,item);
+	}
+
+	fn visit_mac(&mut self, _mac: &'v Mac){
+		visit::walk_mac (self, _mac)
+	}
+}
+
+impl PreRecord{
+	// The key of the type a type expression of the module that is being
+	// visited names
+	fn type_name(&self, ty:&P<ast::Ty>) -> Option<String>{
+		type_name(ty,&self.alias_record,&self.mod_path,&self.type_keys)
+	}
+
+	// Record the fields of a structure or of an enum variant with their types.
+	// Fields of a tuple structure or tuple variant are keyed by their position
+	fn record_fields(&self, variants: &VariantData) -> HashMap<String,Ty>{
+		let mut fields = HashMap::new();
+		match variants{
+			&VariantData::Struct(ref struct_fields,_)=>{
+				for field in struct_fields{
+					let mut name = " ".to_string();
+					if let Some(ref field_name) = field.ident{
+						name = field_name.name.as_str().to_string();
+					}
+					let ty = classify(&field.ty,&self.alias_record);
+					fields.insert(name,ty);
+				}
+			},
+			&VariantData::Tuple(ref struct_fields,_)=>{
+				let mut index:usize = 0;
+				for field in struct_fields{
+					fields.insert(index.to_string(),classify(&field.ty,&self.alias_record));
+					index+=1;
+				}
+			},
+			_=>(),
+		}
+		fields
+	}
+
+	// Record the names of the types of the fields that own a value which
+	// is not copied, keyed by the name or the index of the field
+	fn record_field_types(&self, variants: &VariantData) -> HashMap<String,String>{
+		let mut field_types = HashMap::new();
+		let struct_fields = match variants{
+			&VariantData::Struct(ref struct_fields,_) | &VariantData::Tuple(ref struct_fields,_) => struct_fields,
+			_ => return field_types,
+		};
+		let mut index:usize = 0;
+		for field in struct_fields{
+			let name = match field.ident{
+				Some(ref field_name) => field_name.name.as_str().to_string(),
+				None => index.to_string(),
+			};
+			index+=1;
+			if let Ty::NonPrimitive = classify(&field.ty,&self.alias_record){
+				if let Some(type_name) = self.type_name(&field.ty){
+					field_types.insert(name,type_name);
+				}
+			}
+		}
+		field_types
+	}
+
+	// Estimate the size of the values the functions take and return by value
+	fn record_sizes(&mut self){
+		for (key, &(ref param_types, ref output_type, ref mod_path)) in &self.fn_types{
+			let info = match self.fun_record.get_mut(key){
+				Some(info) => info,
+				None => continue,
+			};
+			let self_ty = info.self_ty.clone();
+			for (param, ty) in info.params.iter_mut().zip(param_types){
+				if let &Some(ref ty) = ty{
+					param.value_size = type_size(ty,self_ty.as_ref(),mod_path,&self.type_keys,&self.type_decls,&self.alias_record,&mut vec![])
+										.map(|size| (pprust::ty_to_string(ty),size));
+				}
+			}
+			if let &Some(ref ty) = output_type{
+				info.output_size = type_size(ty,self_ty.as_ref(),mod_path,&self.type_keys,&self.type_decls,&self.alias_record,&mut vec![])
+										.map(|size| (pprust::ty_to_string(ty),size));
+			}
+		}
+	}
+
+	// Record the other form of the fields of a structure that own a value
+	// which is not copied, or that hold a reference
+	fn record_field_forms(&self, variants: &VariantData) -> HashMap<String,String>{
+		let mut field_forms = HashMap::new();
+		let struct_fields = match variants{
+			&VariantData::Struct(ref struct_fields,_) | &VariantData::Tuple(ref struct_fields,_) => struct_fields,
+			_ => return field_forms,
+		};
+		let mut index:usize = 0;
+		for field in struct_fields{
+			let name = match field.ident{
+				Some(ref field_name) => field_name.name.as_str().to_string(),
+				None => index.to_string(),
+			};
+			index+=1;
+			let form = match (classify(&field.ty,&self.alias_record), &field.ty.node){
+				(Ty::Ref, &TyKind::Rptr(_, ref mut_ty)) => owned_type(&mut_ty.ty,&self.alias_record),
+				(Ty::NonPrimitive, _) => borrowed_type(&field.ty,&self.alias_record)[1..].to_string(),
+				_ => continue,
+			};
+			field_forms.insert(name,form);
+		}
+		field_forms
+	}
+}
+
+impl AliasRecord{
+	// A name imported without renaming is not an alias
+	fn insert_alias(&mut self, alias:String, target:String){
+		if !String::eq(&alias,&target){
+			self.alias_record.insert(alias,Alias::Name(target));
+		}
+	}
 }
 
 
@@ -37,33 +307,65 @@ impl <'v> Visitor<'v> for PreRecord {
 		match &item.node {
 			// Enum type
 			& ItemKind::Enum(ref ed,_)=>{
-				let mut var_list = vec![];
+				let mut var_list = HashMap::new();
 				for variant in &ed.variants{
 					let variant_name=variant.node.name.name.as_str().to_string();
-					var_list.push(variant_name);
+					var_list.insert(variant_name,self.record_fields(&variant.node.data));
 				}
-				self.enum_record.insert(item.ident.name.as_str().to_string(), var_list);
+				let key = qualify(&self.mod_path,&item.ident.name.as_str());
+				self.enum_record.insert(key.clone(), var_list);
+				let decls = ed.variants.iter().map(|variant| field_decls(&variant.node.data)).collect();
+				self.type_decls.insert(key, decls);
 			},
-			//Structure
+			//Structure, including tuple structure
 			& ItemKind::Struct(ref variants,_)=>{
-				let mut new_struct = HashMap::new();
-				match variants{
-					&VariantData::Struct(ref fields,_)=>{
-						for field in fields{
-							let mut name = " ".to_string();
-							if let Some(ref field_name) = field.ident{
-								name = field_name.name.as_str().to_string();
-							}
-							let ty = classify(&field.ty);
-							new_struct.insert(name,ty);
-						}
-					},
-					_=>(),
+				let key = qualify(&self.mod_path,&item.ident.name.as_str());
+				let new_struct = self.record_fields(variants);
+				self.struct_record.insert(key.clone(),new_struct);
+				let field_types = self.record_field_types(variants);
+				self.field_type_record.insert(key.clone(),field_types);
+				let field_forms = self.record_field_forms(variants);
+				self.field_form_record.insert(key.clone(),field_forms);
+				self.type_decls.insert(key.clone(),vec![field_decls(variants)]);
+				if derives(&item.attrs,"Default"){
+					self.default_record.insert(key);
 				}
-				self.struct_record.insert(item.ident.name.as_str().to_string(),new_struct);
-				let new_method = item.ident.name.as_str().to_string() + "new";
-				let fun_rec = FnInfo{output:Some(Ty::NonPrimitive), input: 0 };
-				self.fun_record.insert(new_method, fun_rec);
+			}
+			// Generic parameters of an impl block apply to all of its methods.
+			// Methods are keyed by the type of the block, and by the trait for
+			// a trait implementation
+			& ItemKind::Impl(_,_,ref generics,ref trait_ref,ref self_ty,_)=>{
+				let mut impl_generics = HashMap::new();
+				record_generics(generics,&mut impl_generics);
+				let outer = mem::replace(&mut self.impl_generics,impl_generics);
+				let (ty, prefix) = impl_prefix(self_ty,trait_ref,&self.alias_record,&self.mod_path,&self.type_keys);
+				if let (&Some(ref ty),&Some(ref trait_ref)) = (&ty,trait_ref){
+					let segments = &trait_ref.path.segments;
+					if String::eq(&segments[segments.len()-1].identifier.name.as_str().to_string(),"Default"){
+						self.default_record.insert(ty.clone());
+					}
+				}
+				let outer_ty = mem::replace(&mut self.self_ty,ty);
+				let outer_prefix = mem::replace(&mut self.fn_prefix,prefix);
+				visit::walk_item(self,item);
+				self.impl_generics = outer;
+				self.self_ty = outer_ty;
+				self.fn_prefix = outer_prefix;
+				return
+			}
+			// Default methods of a trait are keyed by the trait
+			& ItemKind::Trait(..)=>{
+				let outer_prefix = mem::replace(&mut self.fn_prefix,Some(item.ident.name.as_str().to_string()));
+				visit::walk_item(self,item);
+				self.fn_prefix = outer_prefix;
+				return
+			}
+			// Functions and types of a module are keyed by the path of the module
+			& ItemKind::Mod(_)=>{
+				self.mod_path.push(item.ident.name.as_str().to_string());
+				visit::walk_item(self,item);
+				self.mod_path.pop();
+				return
 			}
 			_=>print!(""),
 		}
@@ -75,18 +377,89 @@ impl <'v> Visitor<'v> for PreRecord {
 		match &fk{
 			&FnKind::ItemFn( ref ident,_,_,_,_,_,_)|&FnKind::Method(ref ident,_,_,_) => {
 				let re_ty = fd.output.clone();
-				let mut arg_num = fd.inputs.len();
+				// Generic parameters and their bounds, including where-clauses
+				let mut generics = self.impl_generics.clone();
 				match &fk{
-					&FnKind::Method(_,_,_,_) => arg_num-=1,
+					&FnKind::ItemFn(_,g,_,_,_,_,_) => record_generics(g,&mut generics),
+					&FnKind::Method(_,sig,_,_) => record_generics(&sig.generics,&mut generics),
 					_=>(),
 				}
-				let mut fun_rec = FnInfo{output:None, input: arg_num };
+				let mut params = vec![];
+				let mut param_types = vec![];
+				let mut receiver = None;
+				for arg in &fd.inputs{
+					if arg.is_self(){
+						receiver = Some(match &arg.ty.node{
+							&TyKind::Rptr(_, ref mut_ty) if mut_ty.mutbl == ast::Mutability::Mutable => Mode::MutRef,
+							&TyKind::Rptr(..) => Mode::Ref,
+							_ => Mode::Value,
+						});
+						continue
+					}
+					let mut name = "_".to_string();
+					match &arg.pat.node{
+						&PatKind::Ident(_, ref span_ident, _) => name = span_ident.node.name.as_str().to_string(),
+						_=>(),
+					}
+					let generic = generic_name(&arg.ty,&generics);
+					let ty = classify_generic(&arg.ty,&generics,&self.alias_record);
+					let general = general_borrow(&arg.ty,&self.alias_record);
+					let borrowed = match ty{
+						Ty::NonPrimitive if generic.is_none() => Some(borrowed_type(&arg.ty,&self.alias_record)),
+						_ => None,
+					};
+					let shared = match &arg.ty.node{
+						&TyKind::Rptr(_, ref mut_ty) if mut_ty.mutbl == ast::Mutability::Mutable =>
+							Some(format!("&{}",pprust::ty_to_string(&mut_ty.ty))),
+						_ => None,
+					};
+					param_types.push(match &arg.ty.node{
+						&TyKind::Rptr(..) | &TyKind::Ptr(_) => None,
+						_ => Some(arg.ty.clone()),
+					});
+					params.push(ParamInfo{ name: name, ty: ty, generic: generic, general: general, borrowed: borrowed,
+											shared: shared, value_size: None });
+				}
+				let is_method = match &fk{
+					&FnKind::Method(..) => true,
+					_ => false,
+				};
+				let mut fun_rec = FnInfo{output:None, input: params.len(), output_fields: HashMap::new(),
+											params: params, generics: HashMap::new(), output_name: None, borrowed_output: None,
+											output_size: None,
+											self_ty: if is_method { self.self_ty.clone() } else { None },
+											receiver: receiver };
 				match re_ty{
-					FunctionRetTy::Ty(ref t) =>fun_rec.output = Some(classify(t)) ,
+					FunctionRetTy::Ty(ref t) =>{
+						fun_rec.output = Some(classify_generic(t,&generics,&self.alias_record));
+						fun_rec.output_fields = tuple_fields(t,&self.alias_record);
+						fun_rec.output_name = match self.type_name(t){
+							Some(ref name) if name == "Self" => self.self_ty.clone(),
+							other => other,
+						};
+						fun_rec.borrowed_output = match fun_rec.output{
+							Some(Ty::NonPrimitive) if generic_name(t,&generics).is_none() => Some(borrowed_type(t,&self.alias_record)),
+							_ => None,
+						};
+					},
 					_=>(),
 				};
+				let output_type = match re_ty{
+					FunctionRetTy::Ty(ref t) => match &t.node{
+						&TyKind::Rptr(..) | &TyKind::Ptr(_) => None,
+						_ => Some(t.clone()),
+					},
+					_ => None,
+				};
+				fun_rec.generics = generics;
 				
-				self.fun_record.insert(ident.name.as_str().to_string(), fun_rec);
+				let name = ident.name.as_str().to_string();
+				let key = match self.fn_prefix{
+					Some(ref prefix) if is_method => prefix.clone() + "::" + &name,
+					_ => qualify(&self.mod_path,&name),
+				};
+				self.fn_types.insert(key.clone(), (param_types,output_type,self.mod_path.clone()));
+				self.fun_record.insert(key, fun_rec);
  
 			},
 			_ => (),
@@ -102,25 +475,391 @@ impl <'v> Visitor<'v> for PreRecord {
 // Entry point of this file
 // Start to visit the AST 
 pub fn get_records(krate:&ast::Crate) -> PreRecord{
+	let node_id = NodeId::new(0);
+
+	let mut alias_record = AliasRecord{ alias_record:HashMap::new(), type_keys:HashSet::new(), mod_path:vec![] };
+	alias_record.visit_mod(&krate.module,krate.span,node_id);
+
 	let mut pre_record = 
 		PreRecord{ 
 			fun_record:HashMap::new(),
 			enum_record:HashMap::new(),
 			struct_record:HashMap::new(),
+			field_type_record:HashMap::new(),
+			field_form_record:HashMap::new(),
+			alias_record:alias_record.alias_record,
+			default_record:HashSet::new(),
+			type_keys:alias_record.type_keys,
+			type_decls:HashMap::new(),
+			fn_types:HashMap::new(),
+			impl_generics:HashMap::new(),
+			mod_path:vec![],
+			self_ty:None,
+			fn_prefix:None,
 		};
-	let node_id = NodeId::new(0);
 
 	pre_record.visit_mod(&krate.module,krate.span,node_id);
+	// Sizes are estimated once all the types are known
+	pre_record.record_sizes();
 	pre_record
 }
 
+// Whether an item has the given trait in its #[derive] attributes
+fn derives(attrs:&[ast::Attribute], name:&str) -> bool{
+	attrs.iter().filter(|attr| attr.check_name("derive"))
+		.filter_map(|attr| attr.meta_item_list())
+		.any(|traits| traits.iter().any(|t| t.check_name(name)))
+}
+
+// The key of a function defined in the given modules, for example: util::parse
+pub fn qualify(mod_path:&[String], name:&str) -> String{
+	let mut path = mod_path.to_vec();
+	path.push(name.to_string());
+	path.join("::")
+}
+
+// The type of an impl block and the prefix of the keys of its methods,
+// for example: Point for `impl Point` and <Point as Display> for
+// `impl Display for Point`, or shapes::Point for `impl Point` in shapes
+pub fn impl_prefix(self_ty:&P<ast::Ty>,
+					trait_ref:&Option<ast::TraitRef>,
+					aliases:&HashMap<String,Alias>,
+					mod_path:&[String],
+					types:&HashSet<String>) -> (Option<String>,Option<String>)
+{
+	let ty = type_name(self_ty,aliases,mod_path,types);
+	let prefix = match (&ty,trait_ref){
+		(&Some(ref ty),&Some(ref trait_ref)) => {
+			let segments = &trait_ref.path.segments;
+			let name = segments[segments.len()-1].identifier.name.as_str().to_string();
+			Some(format!("<{} as {}>",ty,name))
+		},
+		(&Some(ref ty),&None) => Some(ty.clone()),
+		_ => None,
+	};
+	(ty,prefix)
+}
+
+// The name of a type, a reference is seen through, for example: Point for
+// &Point. Aliases are followed and a structure or an enum of the program
+// is named by its key, see type_key
+pub fn type_name(ty:&P<ast::Ty>,
+						aliases:&HashMap<String,Alias>,
+						mod_path:&[String],
+						types:&HashSet<String>) -> Option<String>{
+	match &ty.node{
+		&TyKind::Path(_, ref p) => Some(path_type_key(p,aliases,mod_path,types)),
+		&TyKind::Rptr(_, ref mut_ty) => type_name(&mut_ty.ty,aliases,mod_path,types),
+		&TyKind::Paren(ref ty) => type_name(ty,aliases,mod_path,types),
+		_ => None,
+	}
+}
+
+// The key of the structure or enum a path used in the given modules names,
+// for example: shapes::Point for Point in shapes or for shapes::Point
+pub fn path_type_key(p:&ast::Path,
+						aliases:&HashMap<String,Alias>,
+						mod_path:&[String],
+						types:&HashSet<String>) -> String{
+	let mut segments:Vec<String> = p.segments.iter().map(|seg| seg.identifier.name.as_str().to_string()).collect();
+	let last = segments.len()-1;
+	segments[last] = resolve_name(&segments[last],aliases);
+	type_key(&segments.join("::"),mod_path,types)
+}
+
+// The key of the structure or enum a name used in the given modules refers
+// to. The type is looked up in the module and then in the modules around
+// it, a type imported from another module is found by its last name when
+// only one type has that name. Other names are kept as they are
+pub fn type_key(name:&str, mod_path:&[String], types:&HashSet<String>) -> String{
+	let mut path = mod_path.to_vec();
+	loop{
+		let key = qualify(&path,name);
+		if types.contains(&key){
+			return key
+		}
+		if path.pop().is_none(){
+			break
+		}
+	}
+	let suffix = "::".to_string() + name.rsplit("::").next().unwrap_or(name);
+	let candidates:Vec<&String> = types.iter().filter(|key| key.ends_with(&suffix)).collect();
+	if candidates.len() == 1 { candidates[0].clone() } else { name.to_string() }
+}
+
+// A shared reference to an owning type derefs to a reference to the type
+// it owns, which accepts more arguments, for example: &str for &String,
+// &[T] for &Vec<T> and &T for &Box<T>
+pub fn general_borrow(ty:&P<ast::Ty>, aliases:&HashMap<String,Alias>) -> Option<(String,String)>{
+	match &ty.node{
+		&TyKind::Rptr(_, ref mut_ty) if mut_ty.mutbl == ast::Mutability::Immutable => {
+			general_type(&mut_ty.ty,aliases).map(|general| (pprust::ty_to_string(ty),general))
+		},
+		_ => None,
+	}
+}
+
+// The type to borrow a value of the given type as, for example: &str for
+// String and &Point for Point
+pub fn borrowed_type(ty:&P<ast::Ty>, aliases:&HashMap<String,Alias>) -> String{
+	match general_type(ty,aliases){
+		Some(general) => general,
+		None => format!("&{}",pprust::ty_to_string(ty)),
+	}
+}
+
+// The types of the fields of a structure or of a variant
+fn field_decls(variants: &VariantData) -> Vec<P<ast::Ty>>{
+	match variants{
+		&VariantData::Struct(ref struct_fields,_) | &VariantData::Tuple(ref struct_fields,_) =>
+			struct_fields.iter().map(|field| field.ty.clone()).collect(),
+		_ => vec![],
+	}
+}
+
+// Estimate the size in bytes of a value of a type on a 64-bit target, from
+// the known layouts of the std types and the fields of the types of the
+// program. None if the size is not known, for example: a generic type.
+// Names are looked up from the given modules
+fn type_size(ty:&P<ast::Ty>,
+				self_ty:Option<&String>,
+				mod_path:&[String],
+				types:&HashSet<String>,
+				decls:&HashMap<String,Vec<Vec<P<ast::Ty>>>>,
+				aliases:&HashMap<String,Alias>,
+				visiting:&mut Vec<String>) -> Option<usize>
+{
+	type_layout(ty,self_ty,mod_path,types,decls,aliases,visiting).map(|(size,_)| size)
+}
+
+// Estimate the size and the alignment in bytes of a value of a type, the
+// alignment of a compound type is the largest alignment of its parts
+fn type_layout(ty:&P<ast::Ty>,
+				self_ty:Option<&String>,
+				mod_path:&[String],
+				types:&HashSet<String>,
+				decls:&HashMap<String,Vec<Vec<P<ast::Ty>>>>,
+				aliases:&HashMap<String,Alias>,
+				visiting:&mut Vec<String>) -> Option<(usize,usize)>
+{
+	match &ty.node{
+		&TyKind::Rptr(_, ref mut_ty) | &TyKind::Ptr(ref mut_ty) => match &mut_ty.ty.node{
+			// A reference to a slice or a str also holds the length
+			&TyKind::Slice(_) => Some((16,8)),
+			&TyKind::Path(None, ref p) if resolve_name(&p.segments[p.segments.len()-1].identifier.name.as_str(),aliases) == "str" => Some((16,8)),
+			_ => Some((8,8)),
+		},
+		&TyKind::Array(ref elem, ref len) => {
+			let count = match &len.node{
+				&ast::ExprKind::Lit(ref lit) => match lit.node{
+					ast::LitKind::Int(count, _) => count as usize,
+					_ => return None,
+				},
+				_ => return None,
+			};
+			type_layout(elem,self_ty,mod_path,types,decls,aliases,visiting).map(|(size,align)| (size*count,align))
+		},
+		&TyKind::Tup(ref elems) => fields_layout(elems,self_ty,mod_path,types,decls,aliases,visiting),
+		&TyKind::Paren(ref inner) => type_layout(inner,self_ty,mod_path,types,decls,aliases,visiting),
+		&TyKind::Path(None, ref p) => {
+			let segment = &p.segments[p.segments.len()-1];
+			let mut name = resolve_name(&segment.identifier.name.as_str(),aliases);
+			if let Some(&Alias::Type(ref t)) = aliases.get(&name){
+				return type_layout(t,self_ty,mod_path,types,decls,aliases,visiting)
+			}
+			// The type of an impl block is already a key
+			let key = if name == "Self"{
+				name = match self_ty{
+					Some(ty) => ty.clone(),
+					None => return None,
+				};
+				name.clone()
+			}else{
+				path_type_key(p,aliases,mod_path,types)
+			};
+			if name == "Option"{
+				let inner = match first_type_param(segment){
+					Some(inner) => inner,
+					None => return None,
+				};
+				// An Option of a pointer that is never null uses the null
+				// value for None
+				let non_null = match &inner.node{
+					&TyKind::Rptr(..) => true,
+					&TyKind::Path(None, ref p) =>
+						builtin::non_null(&resolve_name(&p.segments[p.segments.len()-1].identifier.name.as_str(),aliases)),
+					_ => false,
+				};
+				return match type_layout(inner,self_ty,mod_path,types,decls,aliases,visiting){
+					Some(layout) if non_null => Some(layout),
+					Some((size,align)) => Some((padded(size+1,align),align)),
+					None => None,
+				}
+			}
+			match builtin::std_size(&name){
+				Some(size) => Some((size,std_alignment(size))),
+				None => type_decl_layout(&key,types,decls,aliases,visiting),
+			}
+		},
+		_ => None,
+	}
+}
+
+// Estimate the layout of a structure or an enum of the program. An enum
+// with more than one variant is as large as its largest variant and a tag
+fn type_decl_layout(name:&str,
+					types:&HashSet<String>,
+					decls:&HashMap<String,Vec<Vec<P<ast::Ty>>>>,
+					aliases:&HashMap<String,Alias>,
+					visiting:&mut Vec<String>) -> Option<(usize,usize)>
+{
+	let variants = match decls.get(name){
+		Some(variants) => variants,
+		None => return None,
+	};
+	// A type cannot contain itself without a pointer in between
+	if visiting.iter().any(|other| other == name){
+		return None
+	}
+	visiting.push(name.to_string());
+	// The fields are named from the module of the type
+	let mut mod_path:Vec<String> = name.split("::").map(|segment| segment.to_string()).collect();
+	mod_path.pop();
+	let mut largest = 0;
+	let mut align = 1;
+	for fields in variants{
+		match fields_layout(fields,None,&mod_path,types,decls,aliases,visiting){
+			Some((size,field_align)) => {
+				if size > largest { largest = size }
+				if field_align > align { align = field_align }
+			},
+			None => {
+				visiting.pop();
+				return None
+			},
+		}
+	}
+	visiting.pop();
+	if variants.len() == 1{
+		Some((largest,align))
+	}else if largest == 0{
+		Some((1,1))
+	}else{
+		Some((padded(largest+1,align),align))
+	}
+}
+
+// The layout of values laid out one after the other, padded to the
+// alignment of the most aligned of them
+fn fields_layout(tys:&[P<ast::Ty>],
+				self_ty:Option<&String>,
+				mod_path:&[String],
+				types:&HashSet<String>,
+				decls:&HashMap<String,Vec<Vec<P<ast::Ty>>>>,
+				aliases:&HashMap<String,Alias>,
+				visiting:&mut Vec<String>) -> Option<(usize,usize)>
+{
+	let mut total = 0;
+	let mut align = 1;
+	for ty in tys{
+		let (size,field_align) = match type_layout(ty,self_ty,mod_path,types,decls,aliases,visiting){
+			Some(layout) => layout,
+			None => return None,
+		};
+		total+=size;
+		if field_align > align{
+			align = field_align;
+		}
+	}
+	Some((padded(total,align),align))
+}
+
+// The alignment of a std type of the given size, a number is aligned to
+// its size and a pointer or a collection to 8 bytes
+fn std_alignment(size:usize) -> usize{
+	let mut align = 1;
+	while (align < 8) & (size > 0) && (size % (align*2) == 0){
+		align*=2;
+	}
+	align
+}
+
+fn padded(size:usize, align:usize) -> usize{
+	(size+align-1)/align*align
+}
+
+// The first type parameter of a path segment, for example: T for Option<T>
+fn first_type_param(segment:&ast::PathSegment) -> Option<&P<ast::Ty>>{
+	match segment.parameters{
+		Some(ref parameters) => match **parameters{
+			ast::PathParameters::AngleBracketed(ref data) => data.types.first(),
+			_ => None,
+		},
+		None => None,
+	}
+}
+
+// The owning type of the value a reference refers to, for example:
+// String for str and Vec<T> for [T]
+fn owned_type(referent:&P<ast::Ty>, aliases:&HashMap<String,Alias>) -> String{
+	match &referent.node{
+		&TyKind::Slice(ref elem) => format!("Vec<{}>",pprust::ty_to_string(elem)),
+		&TyKind::Path(None, ref p) if (p.segments.len() == 1) && (resolve_name(&p.segments[0].identifier.name.as_str(),aliases) == "str") =>
+			"String".to_string(),
+		_ => pprust::ty_to_string(referent),
+	}
+}
+
+// The more general reference an owning type derefs to
+fn general_type(inner:&P<ast::Ty>, aliases:&HashMap<String,Alias>) -> Option<String>{
+	let segment = match &inner.node{
+		&TyKind::Path(None, ref p) => &p.segments[p.segments.len()-1],
+		_ => return None,
+	};
+	let param = match segment.parameters{
+		Some(ref parameters) => match **parameters{
+			ast::PathParameters::AngleBracketed(ref data) => data.types.first().map(|t| pprust::ty_to_string(t)),
+			_ => None,
+		},
+		None => None,
+	};
+	let general = match (resolve_name(&segment.identifier.name.as_str(),aliases).as_str(),param){
+		("String",_) => "&str".to_string(),
+		("Vec",Some(param)) => format!("&[{}]",param),
+		("Box",Some(param)) => format!("&{}",param),
+		_ => return None,
+	};
+	Some(general)
+}
+
+// Follow type aliases and renamed imports until reaching the original name.
+// A name that is not an alias is returned unchanged
+pub fn resolve_name (name: &str, aliases: &HashMap<String,Alias>) -> String{
+	let mut name = name.to_string();
+	// Aliases cannot be cyclic, but never follow more than all of them
+	let mut count = 0;
+	while count < aliases.len(){
+		match aliases.get(&name){
+			Some(&Alias::Name(ref target)) => name = target.clone(),
+			_ => break,
+		}
+		count+=1;
+	}
+	name
+}
+
 // Classify the return_type that is defined in the ast.rs of Rust
 // into the type that is needed in this tool
-pub fn classify (re_ty: & P<ast::Ty>) -> Ty{
-	let ref return_type = re_ty.node;
+// Aliases are followed so that `type Id = u64;` is classified as primitive
+pub fn classify (re_ty: & P<ast::Ty>, aliases: &HashMap<String,Alias>) -> Ty{
+	let return_type = &re_ty.node;
 	match return_type{
 		&TyKind::Path( _ , ref p) =>{
-			let ident = p.segments[0].identifier.name.as_str().to_string();
+			let ident = p.segments[p.segments.len()-1].identifier.name.as_str().to_string();
+			let ident = resolve_name(&ident,aliases);
+			if let Some(&Alias::Type(ref t)) = aliases.get(&ident){
+				return classify(t,aliases)
+			}
 			let i = ident.as_str(); 
 			match i{
 				"i32" | "bool" | "char"| "f32"| "f64" | "i16" |"i64" |"i8" | "isize" |
@@ -129,8 +868,140 @@ pub fn classify (re_ty: & P<ast::Ty>) -> Ty{
 			}	
 		},
 		&TyKind::Rptr( _ , _ ) |&TyKind::Ptr(_) => Ty::Ref,
-		&TyKind::Slice(ref t) => classify(t),
-		&TyKind::Array(ref t , _ ) => classify(t),
+		&TyKind::Slice(ref t) | &TyKind::Array(ref t , _ ) => classify(t,aliases),
+		// A tuple is Copy only when all of its elements are primitive
+		&TyKind::Tup(ref tys) =>{
+			for t in tys{
+				match classify(t,aliases){
+					Ty::Primitive => (),
+					_ => return Ty::NonPrimitive,
+				}
+			}
+			Ty::Primitive
+		},
 		_ =>Ty::NonPrimitive,
 	}
-}
\ No newline at end of file
+}
+
+// Get the positional fields of a tuple type, keyed by their index.
+// Return an empty map when the type is not a tuple
+pub fn tuple_fields (re_ty: & P<ast::Ty>, aliases: &HashMap<String,Alias>) -> HashMap<String,Ty>{
+	let mut fields = HashMap::new();
+	match &re_ty.node{
+		&TyKind::Tup(ref tys) =>{
+			let mut index:usize = 0;
+			for t in tys{
+				fields.insert(index.to_string(),classify(t,aliases));
+				index+=1;
+			}
+		},
+		&TyKind::Path( _ , ref p) =>{
+			let ident = p.segments[p.segments.len()-1].identifier.name.as_str().to_string();
+			if let Some(&Alias::Type(ref t)) = aliases.get(&resolve_name(&ident,aliases)){
+				return tuple_fields(t,aliases)
+			}
+		},
+		_ => (),
+	}
+	fields
+}
+
+// Record the generic type parameters and the traits that bound them,
+// from both the parameter list and the where-clause
+fn record_generics(generics: &Generics, record: &mut HashMap<String,Vec<String>>){
+	for ty_param in &generics.ty_params{
+		let bounds = record.entry(ty_param.ident.name.as_str().to_string()).or_insert_with(Vec::new);
+		for bound in &ty_param.bounds{
+			if let Some(trait_name) = bound_name(bound){
+				bounds.push(trait_name);
+			}
+		}
+	}
+	for predicate in &generics.where_clause.predicates{
+		match predicate{
+			&WherePredicate::BoundPredicate(ref bound_predicate)=>{
+				match &bound_predicate.bounded_ty.node{
+					&TyKind::Path(None, ref p) if p.segments.len() == 1 =>{
+						let param = p.segments[0].identifier.name.as_str().to_string();
+						if let Some(bounds) = record.get_mut(&param){
+							for bound in &bound_predicate.bounds{
+								if let Some(trait_name) = bound_name(bound){
+									bounds.push(trait_name);
+								}
+							}
+						}
+					},
+					_=>(),
+				}
+			},
+			_=>(),
+		}
+	}
+}
+
+// Name of the trait in a bound, lifetime bounds have no name
+fn bound_name(bound: &TyParamBound) -> Option<String>{
+	match bound{
+		&TyParamBound::TraitTyParamBound(ref poly_trait, _)=>{
+			let segments = &poly_trait.trait_ref.path.segments;
+			Some(segments[segments.len()-1].identifier.name.as_str().to_string())
+		},
+		_=>None,
+	}
+}
+
+// Name of the generic type parameter that a type refers to, if any
+pub fn generic_name (re_ty: & P<ast::Ty>, generics: &HashMap<String,Vec<String>>) -> Option<String>{
+	match &re_ty.node{
+		&TyKind::Path(None, ref p) if p.segments.len() == 1 =>{
+			let ident = p.segments[0].identifier.name.as_str().to_string();
+			if generics.contains_key(&ident){
+				Some(ident)
+			}else{
+				None
+			}
+		},
+		_=>None,
+	}
+}
+
+// Check whether a generic type parameter is bounded by the given trait
+pub fn has_bound (param: &str, bound: &str, generics: &HashMap<String,Vec<String>>) -> bool{
+	match generics.get(param){
+		Some(bounds) => bounds.iter().any(|b| String::eq(b,bound)),
+		None => false,
+	}
+}
+
+// Classify a type that may be a generic type parameter. A parameter
+// bounded by Copy behaves like a primitive, any other one can be moved
+pub fn classify_generic (re_ty: & P<ast::Ty>,
+						generics: &HashMap<String,Vec<String>>,
+						aliases: &HashMap<String,Alias>) -> Ty{
+	match generic_name(re_ty,generics){
+		Some(ref param) if has_bound(param,"Copy",generics) => Ty::Primitive,
+		Some(_) => Ty::NonPrimitive,
+		None => classify(re_ty,aliases),
+	}
+}
+
+// Get the fields of the structure or enum variant that a path names,
+// for example: Point, Msg::Text or Msg::Move, used in the given modules
+pub fn path_fields<'a> (p: &ast::Path,
+						enum_list: &'a HashMap<String,HashMap<String,HashMap<String,Ty>>>,
+						structure_list: &'a HashMap<String,HashMap<String,Ty>>,
+						aliases: &HashMap<String,Alias>,
+						mod_path: &[String],
+						types: &HashSet<String>) -> Option<&'a HashMap<String,Ty>>{
+	let len = p.segments.len();
+	let last = resolve_name(&p.segments[len-1].identifier.name.as_str().to_string(),aliases);
+	if len >= 2{
+		let mut segments:Vec<String> = p.segments[..len-1].iter().map(|seg| seg.identifier.name.as_str().to_string()).collect();
+		segments[len-2] = resolve_name(&segments[len-2],aliases);
+		let enum_name = type_key(&segments.join("::"),mod_path,types);
+		if let Some(variants) = enum_list.get(&enum_name){
+			return variants.get(&last)
+		}
+	}
+	structure_list.get(&path_type_key(p,aliases,mod_path,types))
+}
@@ -10,18 +10,36 @@ use SymbolTable;
 use syntax::ast::Path;
 use std::collections::HashMap;
 use syntax::ast::{UnOp,Expr,ExprKind,Block,StmtKind};
-#[derive(Debug)]
+use syntax::ptr::P;
+#[derive(Debug,Clone)]
 pub struct Resolver{
     pub var_name: Option<Vec<String>>,
     pub var_type: Option<Ty>,
     pub ref_to:Option<Vec<String>>,
     pub structure:HashMap<String,Resolver>,
+    // Name of the type of the expression, if it is known
+    pub type_name:Option<String>,
 }
 
 impl Resolver{
+    // Record the given fields with their types as the structure of
+    // this expression
+    fn build_fields(&mut self, fields:&HashMap<String,Ty>){
+        for (field_name, field_type) in fields{
+            let field_resolver = Resolver{
+                var_name:None,
+                var_type:Some(field_type.clone()),
+                ref_to:None,
+                structure:HashMap::new(),
+                type_name:None,
+            };
+            self.structure.insert(field_name.clone(),field_resolver);
+        }
+    }
+
     fn resolve_path_for_var(&mut self, 
                                             p: &Path, 
-                                            enum_list:& HashMap<String,Vec<String>>,
+                                            enum_list:& HashMap<String,HashMap<String,HashMap<String,Ty>>>,
                                             symbol_table:&SymbolTable,
                     
                                             )
@@ -35,11 +53,20 @@ impl Resolver{
                     self.var_type=Some(Ty::Primitive);
                     return
                 }
+                // The receiver of a method has the type of the impl block
+                if String::eq(&ident,"self"){
+                    self.type_name = symbol_table.self_type.clone();
+                }
                 // Get name of variable 
                 let var = vec![p.segments[0].identifier.name.as_str().to_string()];
                 //Check whether this var is in symbol_table
                 if symbol_table.check_var(&var){
                     self.var_type = Some(symbol_table.get_var_type(&var));
+                    // A copy of a reference refers to the same variable
+                    if let Some(Ty::Ref) = self.var_type{
+                        self.ref_to = symbol_table.get_ref_to(&var);
+                    }
+                    self.type_name = symbol_table.get_type_name(&var);
                     self.var_name= Some(var.clone());
                 }
             }
@@ -47,13 +74,13 @@ impl Resolver{
             // declared before. If it represent a enum type, then record this expression
             // as NonPrimitive
             2 => {
-                if let Some(coms) = enum_list.get(&p.segments[0].identifier.name.as_str().to_string()){
+                let enum_name = pre_record::resolve_name(&p.segments[0].identifier.name.as_str().to_string(),symbol_table.alias_list);
+                let enum_name = pre_record::type_key(&enum_name,&symbol_table.mod_path,symbol_table.type_keys);
+                if let Some(coms) = enum_list.get(&enum_name){
                     let expect = p.segments[1].identifier.name.as_str().to_string(); 
-                    for com in coms{
-                        if String::eq(&com,&expect){
-                                self.var_type=Some(Ty::NonPrimitive);
-                                break
-                        }
+                    if coms.contains_key(&expect){
+                        self.var_type=Some(Ty::NonPrimitive);
+                        self.type_name = Some(enum_name.clone());
                     }
                 }
             }
@@ -62,18 +89,37 @@ impl Resolver{
             _ => (),
         }
     }
+    // Record the variable referred by the first argument that is a
+    // reference as the variable this expression refers to
+    fn ref_from_args(&mut self,
+                                  args:&[P<Expr>],
+                                  enum_list:& HashMap<String,HashMap<String,HashMap<String,Ty>>>,
+                                  fun_records:&HashMap<String, FnInfo>,
+                                  symbol_table:&SymbolTable,
+                                  structure_list: &HashMap<String,HashMap<String,Ty>>)
+    {
+        for arg in args{
+            let arg_resolver = resolve_expr(arg,enum_list,fun_records,symbol_table,structure_list);
+            if arg_resolver.ref_to.is_some(){
+                self.ref_to = arg_resolver.ref_to;
+                return
+            }
+        }
+    }
+
     // Only consider the situation that the last statement of block is 
     // an expression. The type of block depends on the type of last expression.
     fn resolve_block_for_var(&mut self,
                                              block:&Block, 
-                                             enum_list:& HashMap<String,Vec<String>>,
+                                             enum_list:& HashMap<String,HashMap<String,HashMap<String,Ty>>>,
                                              fun_records:&HashMap<String, FnInfo>,
                                              symbol_table:&SymbolTable,
                                              structure_list: &HashMap<String,HashMap<String,Ty>>)
     {
-        let stmts = &block.stmts;
-        let length = stmts.len();
-        let stmt = &stmts[length-1];
+        let stmt = match block.stmts.last(){
+            Some(stmt) => stmt,
+            None => return,
+        };
         match &stmt.node {
             &StmtKind::Expr(ref ex) => self.resolve_expr_for_var(ex,enum_list,fun_records,symbol_table,structure_list),
             _=>() 
@@ -84,7 +130,7 @@ impl Resolver{
     // Resolve several kinds of expressions
     fn resolve_expr_for_var(&mut self,
                                              ex:&Expr, 
-                                             enum_list:& HashMap<String,Vec<String>>,
+                                             enum_list:& HashMap<String,HashMap<String,HashMap<String,Ty>>>,
                                              fun_records:&HashMap<String, FnInfo>,
                                              symbol_table:&SymbolTable,
                                              structure_list: &HashMap<String,HashMap<String,Ty>>
@@ -95,6 +141,16 @@ impl Resolver{
             &ExprKind::Path(_, ref p) => self.resolve_path_for_var(p,enum_list,symbol_table),
             //Reference
             &ExprKind::AddrOf(_ ,ref e) => {
+                // A reborrow, for example: &*r, refers to the variable that
+                // r refers to
+                if let &ExprKind::Unary(UnOp::Deref, ref inner) = &e.node{
+                    let reborrow = resolve_expr(inner,enum_list,fun_records,symbol_table,structure_list);
+                    if reborrow.ref_to.is_some(){
+                        self.ref_to = reborrow.ref_to;
+                        self.var_type = Some(Ty::Ref);
+                        return
+                    }
+                }
                 self.resolve_expr_for_var(e,enum_list,fun_records,symbol_table,structure_list);
                 // If the path in a reference point to a declared variable,then
                 // set var_type to Ref and var_name to none, and set ref_to to the 
@@ -102,18 +158,11 @@ impl Resolver{
                 if let Some(ref var) = self.var_name{
                     self.ref_to = Some(var.clone());
                     self.var_type = Some(Ty::Ref);
-                } else{
+                } else if let Some(Ty::NonPrimitive) = self.var_type{
                     // If the path does no point to a specified variable, then if 
                     // the path point to a NonPrimitive type then set var_type to
                     //ref.
-                    if let Some(ref mut ty) = self.var_type{
-                        match ty{
-                            &mut Ty::NonPrimitive => {
-                                *ty = Ty::Ref;
-                            },
-                            _ => (), 
-                        }
-                    }
+                    self.var_type = Some(Ty::Ref);
                 }
                 self.var_name = None;
             },
@@ -132,58 +181,70 @@ impl Resolver{
                                         var_type:None,
                                         ref_to:None,
                                         structure:HashMap::new(),
+                                        type_name:None,
                                         };
                             arg_resolver.resolve_expr_for_var(&args[0],enum_list,fun_records,symbol_table,structure_list);
                             self.var_type = arg_resolver.var_type;
                             return
                         }
-                        let funs = p.segments.clone();
-                        let mut function_name = " ".to_string(); 
-                        for fun in funs {
-                            function_name = function_name+ &(fun.identifier.name.as_str().to_string());
+                        // Constructor of a tuple structure or of an enum variant with
+                        // payload, for example: Point(x, y) or Msg::Text(s).
+                        // Arguments become positional fields like the fields of a
+                        // structure literal
+                        if let Some(fields) = pre_record::path_fields(p,enum_list,structure_list,symbol_table.alias_list,&symbol_table.mod_path,symbol_table.type_keys){
+                            self.var_type = Some(Ty::NonPrimitive);
+                            self.type_name = constructed_type(p,enum_list,symbol_table);
+                            let mut index:usize = 0;
+                            for arg in args{
+                                let mut field_resolver=Resolver{
+                                    var_name:None,
+                                    var_type:None,
+                                    ref_to:None,
+                                    structure:HashMap::new(),
+                                    type_name:None,
+                                };
+                                field_resolver.resolve_expr_for_var(arg,enum_list,fun_records,symbol_table,structure_list);
+                                // Arguments are used and moved by the call itself
+                                field_resolver.var_name = None;
+                                if field_resolver.var_type.is_none(){
+                                    field_resolver.var_type = fields.get(&index.to_string()).cloned();
+                                }
+                                self.structure.insert(index.to_string(),field_resolver);
+                                index+=1;
+                            }
+                            return
                         }
- 
-                        let function_name = function_name.trim();
-                     
+                        let segments:Vec<String> = p.segments.iter().map(|seg| seg.identifier.name.as_str().to_string()).collect();
                         // Builtin need to change         
                         let mut return_type = None;
-                        if let Some(ref info) = fun_records.get(function_name) {
-                            // Check whether  the method is a constructor of a structure
-                            let mut is_constructor = false;
-                            let mut structure_name ="";
-                            for(struct_name, _ ) in structure_list{
-                                let new_method = struct_name.to_string() +"new";
-                                if String::eq(&new_method,&function_name){
-                                    is_constructor= true;
-                                    structure_name = struct_name;
-                                    break;
-                                }
-                            } 
- 
-                            if is_constructor{
-                                self.var_type = Some(Ty::NonPrimitive);
-                                if let Some(fields) = structure_list.get(structure_name){
-                                    for (field_name, field_type) in fields{
-                                        let field_resolver = Resolver{
-                                            var_name:None,
-                                            var_type:Some(field_type.clone()),
-                                            ref_to:None,
-                                            structure:HashMap::new(),
-                                        };
-                                        self.structure.insert(field_name.clone(),field_resolver);                
+                        if let Some(info) = symbol_table.function_key(&segments).and_then(|key| fun_records.get(&key)) {
+                            if let Some(ref re_ty) = info.output{
+                                return_type = Some(re_ty.clone());
+                                self.build_fields(&info.output_fields);
+                            }
+                            // A function returning a structure, for example a
+                            // constructor, returns all of its fields
+                            if let Some(ref name) = info.output_name{
+                                if info.output_fields.is_empty(){
+                                    if let Some(fields) = structure_list.get(name){
+                                        self.build_fields(fields);
                                     }
-
                                 }
-                            }else if let Some(ref re_ty) = info.output{
-                                return_type = Some(re_ty.clone());
                             }
+                            self.type_name = info.output_name.clone();
                         }else{
-                            return_type =Some(builtin::get_func_rety(function_name, None));
+                            // Aliases are followed so that `Text::new` is seen as `String::new`
+                            let function_name:Vec<String> = segments.iter().map(|seg| pre_record::resolve_name(seg,symbol_table.alias_list)).collect();
+                            return_type =Some(builtin::get_func_rety(symbol_table.signatures, &builtin::function_key(&function_name), None, None));
                         }
                         match &self.var_type{
                             &None => self.var_type = return_type,
                             _=>(),
                         }
+                        // A returned reference borrows from a reference argument
+                        if let Some(Ty::Ref) = self.var_type{
+                            self.ref_from_args(args,enum_list,fun_records,symbol_table,structure_list);
+                        }
                         
                     },
                      _ =>(),
@@ -194,26 +255,43 @@ impl Resolver{
                 // Get function name 
                 let function = func_name.node.name.as_str().to_string();
             
-                let ref receiver = args[0] ;
+                let receiver = &args[0] ;
                 // Use a new resolver to resolve receiver
                 let mut receiver_resolver = Resolver{
                     var_name:None,
                     var_type:None,
                     ref_to:None,
                     structure:HashMap::new(),
+                    type_name:None,
                 };
 
                 let mut return_type = None;    
                
                 receiver_resolver.resolve_expr_for_var(receiver,enum_list,fun_records,symbol_table,structure_list);
                 
-                if let Some(info) = fun_records.get(&function) {
+                // The method is looked up by the type of the receiver
+                let key = symbol_table.method_key(receiver_resolver.type_name.as_ref(),&function);
+                if let Some(info) = key.and_then(|key| fun_records.get(&key)) {
                     if let Some(ref re_ty) = info.output{
                         return_type = Some(re_ty.clone());
+                        self.build_fields(&info.output_fields);
                     }
+                    self.type_name = info.output_name.clone();
                 }else{
                     let receiver_type = receiver_resolver.var_type.clone();
-                    return_type =Some(builtin::get_func_rety(&function, receiver_type));
+                    return_type =Some(builtin::get_func_rety(symbol_table.signatures, &function, receiver_type, receiver_resolver.type_name.as_ref()));
+                }
+
+                // A returned reference borrows from the receiver, or from a
+                // reference argument
+                if let Some(Ty::Ref) = return_type{
+                    self.ref_to = match receiver_resolver.ref_to{
+                        Some(var) => Some(var),
+                        None => receiver_resolver.var_name.clone(),
+                    };
+                    if self.ref_to.is_none(){
+                        self.ref_from_args(&args[1..],enum_list,fun_records,symbol_table,structure_list);
+                    }
                 }
 
                 self.var_type = return_type;
@@ -246,11 +324,18 @@ impl Resolver{
                 match uo{
                     &UnOp::Deref=>{
                         self.resolve_expr_for_var(ex,enum_list,fun_records,symbol_table,structure_list);
-                        if let Some(ref var) = self.ref_to{
-                            self.var_type = Some(symbol_table.get_var_type(var));
-                        } else {
-                            panic!{"{:?} can not be dereferenced!",self.var_name}
-                        }
+                        // A reference that does not refer to a known variable,
+                        // for example a parameter, points to a value of its
+                        // declared type. A value that is read through a
+                        // reference of an unknown type is copied
+                        self.var_type = match (&self.ref_to, &self.var_name){
+                            (&Some(ref var), _) => Some(symbol_table.get_var_type(var)),
+                            (&None, &Some(ref var)) if var.len() == 1 =>
+                                Some(symbol_table.get_pointee(&var[0]).unwrap_or(Ty::Primitive)),
+                            _ => Some(Ty::Primitive),
+                        };
+                        // The value read through the reference is not a reference
+                        self.ref_to = None;
                         self.var_name =None;
                     },
                     _=>self.var_type = Some(Ty::Primitive),
@@ -258,20 +343,22 @@ impl Resolver{
             }
 
             &ExprKind::Cast(_,ref ty) =>{
-                let return_type = pre_record::classify(ty);
+                let return_type = pre_record::classify(ty,symbol_table.alias_list);
                 self.var_type = Some(return_type);
             },
              &ExprKind::Index(ref expr,_)=>{
                 self.resolve_expr_for_var(expr,enum_list,fun_records,symbol_table,structure_list);
              },
-             &ExprKind::Struct(_, ref fields, _ )=>{
+             &ExprKind::Struct(ref p, ref fields, _ )=>{
                 self.var_type = Some(Ty::NonPrimitive);
+                self.type_name = constructed_type(p,enum_list,symbol_table);
                 for field in fields{
                     let mut field_resolver=Resolver{
                         var_name:None,
                         var_type:None,
                         ref_to:None,
                         structure:HashMap::new(),
+                        type_name:None,
                     };
                     field_resolver.resolve_expr_for_var(&field.expr,enum_list,fun_records,symbol_table,structure_list);
                     field_resolver.var_name = None;
@@ -285,15 +372,69 @@ impl Resolver{
                         var_type:None,
                         ref_to:None,
                         structure:HashMap::new(),
+                        type_name:None,
                     };
                     tem_resolver.resolve_expr_for_var(ex,enum_list,fun_records,symbol_table,structure_list);
                     if let Some(mut rest) = tem_resolver.var_name{
                         rest.append(&mut var);
                         self.var_type=Some(symbol_table.get_var_type(&rest));
+                        // A field holding a reference refers to a variable as well
+                        if let Some(Ty::Ref) = self.var_type{
+                            self.ref_to = symbol_table.get_ref_to(&rest);
+                        }
                         self.var_name = Some(rest);
 
                     }
              },
+             // Tuple, for example: (a, b). Each element is recorded as a positional
+             // field so that `t.0` can be tracked like a field of a structure.
+             // A tuple whose elements are all primitive is Copy.
+             &ExprKind::Tup(ref exprs)=>{
+                let mut all_primitive = true;
+                let mut index:usize = 0;
+                for expr in exprs{
+                    let mut field_resolver=Resolver{
+                        var_name:None,
+                        var_type:None,
+                        ref_to:None,
+                        structure:HashMap::new(),
+                        type_name:None,
+                    };
+                    field_resolver.resolve_expr_for_var(expr,enum_list,fun_records,symbol_table,structure_list);
+                    match field_resolver.var_type{
+                        Some(Ty::Primitive) => (),
+                        _ => all_primitive = false,
+                    }
+                    self.structure.insert(index.to_string(),field_resolver);
+                    index+=1;
+                }
+                if all_primitive{
+                    self.var_type = Some(Ty::Primitive);
+                }else{
+                    self.var_type = Some(Ty::NonPrimitive);
+                }
+             },
+             // Positional field of a tuple, for example: t.0
+             &ExprKind::TupField(ref ex, ref index)=>{
+                    let mut var = vec![index.node.to_string()];
+                    let mut tem_resolver=Resolver{
+                        var_name:None,
+                        var_type:None,
+                        ref_to:None,
+                        structure:HashMap::new(),
+                        type_name:None,
+                    };
+                    tem_resolver.resolve_expr_for_var(ex,enum_list,fun_records,symbol_table,structure_list);
+                    if let Some(mut rest) = tem_resolver.var_name{
+                        rest.append(&mut var);
+                        self.var_type=Some(symbol_table.get_var_type(&rest));
+                        // A field holding a reference refers to a variable as well
+                        if let Some(Ty::Ref) = self.var_type{
+                            self.ref_to = symbol_table.get_ref_to(&rest);
+                        }
+                        self.var_name = Some(rest);
+                    }
+             },
              // &ExprKind::Mac(ref mac)=>{
              // }
             _=>(),
@@ -304,7 +445,7 @@ impl Resolver{
 // Entry point of this file.
 // Takes the input expression, resolves it and return needed information 
 pub fn resolve_expr(ex:& Expr, 
-                                    enum_list:& HashMap<String,Vec<String>>,
+                                    enum_list:& HashMap<String,HashMap<String,HashMap<String,Ty>>>,
                                     fun_records:&HashMap<String, FnInfo>,
                                     symbol_table:&SymbolTable,
                                     structure_list: &HashMap<String,HashMap<String,Ty>>)
@@ -315,8 +456,29 @@ pub fn resolve_expr(ex:& Expr,
         var_type:None,
         ref_to:None,
         structure:HashMap::new(),
+        type_name:None,
     };
 
     resolve.resolve_expr_for_var(ex,enum_list,fun_records,symbol_table,structure_list);
     resolve
-}
\ No newline at end of file
+}
+// The type built by a structure literal or a constructor, for example:
+// Point for `Point { .. }` and Msg for `Msg::Text(s)`
+fn constructed_type(p:&Path,
+                                enum_list:& HashMap<String,HashMap<String,HashMap<String,Ty>>>,
+                                symbol_table:&SymbolTable) -> Option<String>
+{
+    let segments:Vec<String> = p.segments.iter()
+                    .map(|seg| pre_record::resolve_name(&seg.identifier.name.as_str(),symbol_table.alias_list)).collect();
+    let len = segments.len();
+    if len > 1{
+        let enum_name = pre_record::type_key(&segments[..len-1].join("::"),&symbol_table.mod_path,symbol_table.type_keys);
+        if enum_list.contains_key(&enum_name){
+            return Some(enum_name)
+        }
+    }
+    if segments[len-1] == "Self"{
+        return symbol_table.self_type.clone()
+    }
+    Some(pre_record::type_key(&segments.join("::"),&symbol_table.mod_path,symbol_table.type_keys))
+}
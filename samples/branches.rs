@@ -0,0 +1,20 @@
+fn describe(flag: bool, name: String, names: &mut Vec<String>) {
+    if flag {
+        names.push(name);
+    }
+}
+
+fn describe_all(flag: bool, name: String, names: &mut Vec<String>) {
+    if flag {
+        names.push(name);
+    } else {
+        names.insert(0, name);
+    }
+}
+
+fn main() {
+    let mut names = Vec::new();
+    describe(true, String::from("ferris"), &mut names);
+    describe_all(false, String::from("corro"), &mut names);
+    println!("{:?}", names);
+}
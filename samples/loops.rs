@@ -0,0 +1,18 @@
+fn first_long(words: &[String], label: String, found: &mut Vec<String>) {
+    for word in words {
+        if word.is_empty() {
+            continue;
+        }
+        if word.len() > 3 {
+            found.push(label);
+            break;
+        }
+    }
+}
+
+fn main() {
+    let words = vec![String::new(), String::from("ferris")];
+    let mut found = Vec::new();
+    first_long(&words, String::from("long"), &mut found);
+    println!("{:?}", found);
+}
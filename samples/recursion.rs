@@ -0,0 +1,22 @@
+fn is_even(n: u32, log: Vec<String>) -> bool {
+    if n == 0 {
+        return true;
+    }
+    is_odd(n - 1, log)
+}
+
+fn is_odd(n: u32, log: Vec<String>) -> bool {
+    if log.len() > 8 {
+        return false;
+    }
+    if n == 0 {
+        return false;
+    }
+    is_even(n - 1, log)
+}
+
+fn main() {
+    let log = vec![String::from("start")];
+    let even = is_even(4, log.clone());
+    println!("{} {}", even, log.len());
+}
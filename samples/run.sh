@@ -0,0 +1,27 @@
+#!/bin/sh
+# File: samples/run.sh
+# Purpose: Run the advisor on every sample and compare what it prints
+#          with the .expected file of the sample. Run it from the root of
+#          the repository after `cargo build --release`. With --update the
+#          .expected files are written from the current output instead
+# Author : Ziling Zhou (802414)
+
+ADVISOR=${ADVISOR:-target/release/advisor}
+LD_LIBRARY_PATH=$(rustc --print sysroot)/lib
+export LD_LIBRARY_PATH
+
+status=0
+for sample in samples/*.rs; do
+	expected=${sample%.rs}.expected
+	if [ "$1" = "--update" ]; then
+		"$ADVISOR" "$sample" > "$expected"
+		continue
+	fi
+	if "$ADVISOR" "$sample" | diff -u "$expected" - ; then
+		echo "ok: $sample"
+	else
+		echo "FAILED: $sample"
+		status=1
+	fi
+done
+exit $status
@@ -0,0 +1,15 @@
+use std::num::ParseIntError;
+
+fn store(count: &str, name: String, names: &mut Vec<String>) -> Result<u32, ParseIntError> {
+    let n = count.parse::<u32>()?;
+    names.push(name);
+    Ok(n)
+}
+
+fn main() {
+    let mut names = Vec::new();
+    match store("3", String::from("ferris"), &mut names) {
+        Ok(n) => println!("{} {:?}", n, names),
+        Err(e) => println!("{}", e),
+    }
+}
@@ -0,0 +1,209 @@
+ // File: The parameter summaries of the tool
+ // Purpose: Functions defined in this file record what the body of each
+ //                 function does with its parameters while the symbol table
+ //                 is built, and combine the records over the call graph into
+ //                 a summary of each parameter
+ // Author : Ziling Zhou (802414)
+
+use std::collections::HashMap;
+
+use cfg::Place;
+
+// What the body of a function does with a parameter at one point
+#[derive(Debug,Clone,PartialEq)]
+pub enum Effect{
+    Read,
+    Mutated,
+    // The value is moved into a variable or into a function outside
+    // of the program
+    Moved,
+    Returned,
+    // The value is put into a field of a structure or into a collection
+    Stored,
+    // The value is passed to the parameter with the given index of a
+    // function of the program
+    Passed(String,usize),
+    // The value is mutably borrowed for the parameter with the given index
+    // of a function of the program, for example: f(&mut x)
+    Borrowed(String,usize),
+    // A method of the owning type is called on a borrowed value, for
+    // example: s.capacity() on a &String, which &str does not have
+    Concrete,
+}
+
+// Record the effects on the parameters while the body of a function is
+// visited
+#[derive(Debug)]
+pub struct SummaryBuilder{
+    name: String,
+    // The parameters and whether they are taken by value
+    params: Vec<(Place,bool)>,
+    // Effects on a parameter (or a field of it) with the line they are on
+    effects: Vec<(usize,usize,Effect)>,
+}
+
+impl SummaryBuilder{
+    pub fn new(name:String) -> SummaryBuilder{
+        SummaryBuilder{ name: name, params: vec![], effects: vec![] }
+    }
+
+    // The key of the function being summarized
+    pub fn name(&self) -> &str{
+        &self.name
+    }
+
+    pub fn add_param(&mut self, place:Place, by_value:bool){
+        self.params.push((place,by_value));
+    }
+
+    // Record an effect on a place, if the place is a parameter or a field
+    // of one
+    pub fn record(&mut self, place:&Place, line:usize, effect:Effect){
+        let found = self.params.iter().position(|&(ref param,_)| (param.decl == place.decl) & (param.var[0] == place.var[0]));
+        if let Some(index) = found{
+            // The declaration of the parameter is not a mutation
+            if (effect == Effect::Mutated) & (place.var.len() == 1) & (line <= place.decl){
+                return
+            }
+            self.effects.push((index,line,effect));
+        }
+    }
+}
+
+// What a function does with one of its parameters, over all of its paths
+#[derive(Debug,Clone,PartialEq)]
+pub struct ParamSummary{
+    pub name: String,
+    pub read: bool,
+    pub mutated: bool,
+    pub moved: bool,
+    pub returned: bool,
+    pub stored: bool,
+    // Whether the function needs the owning type behind a reference
+    pub concrete: bool,
+    // Whether the parameter is taken by value, a returned reference
+    // parameter does not take the value it refers to
+    pub by_value: bool,
+}
+
+impl ParamSummary{
+    fn new(name:String, by_value:bool) -> ParamSummary{
+        ParamSummary{ name: name, read: false, mutated: false, moved: false, returned: false, stored: false, concrete: false, by_value: by_value }
+    }
+
+    pub fn unused(&self) -> bool{
+        !(self.read | self.mutated | self.moved | self.returned | self.stored)
+    }
+
+    // Whether the function needs the ownership of the value
+    pub fn consumed(&self) -> bool{
+        self.moved | self.returned | self.stored
+    }
+
+    // Merge what a callee does with a value passed to it
+    fn merge(&mut self, other:&ParamSummary) -> bool{
+        let old = self.clone();
+        self.read |= other.read;
+        self.mutated |= other.mutated;
+        // A value returned by the callee goes on to the caller
+        self.moved |= other.moved | (other.returned & other.by_value);
+        self.stored |= other.stored;
+        self.concrete |= other.concrete;
+        old != *self
+    }
+
+    // Merge what a callee does through a mutable borrow of the value, the
+    // value itself stays with the caller
+    fn merge_borrow(&mut self, other:&ParamSummary) -> bool{
+        let old = self.clone();
+        self.read |= other.read;
+        self.mutated |= other.mutated;
+        old != *self
+    }
+
+    // Describe the summary, for example: read, moved
+    pub fn describe(&self) -> String{
+        if self.unused(){
+            return "unused".to_string()
+        }
+        let mut kinds = vec![];
+        if self.read { kinds.push("read") }
+        if self.mutated { kinds.push("mutated") }
+        if self.moved { kinds.push("moved") }
+        if self.returned { kinds.push("returned") }
+        if self.stored { kinds.push("stored") }
+        kinds.join(", ")
+    }
+}
+
+// The summaries of the parameters of a function
+#[derive(Debug)]
+pub struct FnSummary{
+    pub name: String,
+    pub params: Vec<ParamSummary>,
+}
+
+// Entry point of this file
+// Summarize the parameters of every function. The direct effects of the
+// body are combined with the summaries of the functions a parameter is
+// passed to, until nothing changes, so that callees are summarized before
+// their callers and recursive calls end up with everything the recursion
+// can do
+pub fn summarize(builders:&[&SummaryBuilder]) -> HashMap<String,FnSummary>{
+    let mut summaries:HashMap<String,FnSummary> = HashMap::new();
+    for builder in builders{
+        let mut params:Vec<ParamSummary> = builder.params.iter().map(|&(ref param,by_value)| ParamSummary::new(param.var[0].clone(),by_value)).collect();
+        for &(index,line,ref effect) in &builder.effects{
+            let summary = &mut params[index];
+            match effect{
+                &Effect::Read => summary.read = true,
+                &Effect::Mutated => summary.mutated = true,
+                &Effect::Returned => summary.returned = true,
+                &Effect::Stored => summary.stored = true,
+                &Effect::Concrete => summary.concrete = true,
+                // A move on the same line as a more precise effect is the
+                // same move
+                &Effect::Moved => if !builder.effects.iter().any(|&(i,l,ref e)|
+                                        (i == index) & (l == line) & (*e != Effect::Moved) & (*e != Effect::Read)
+                                            & (*e != Effect::Mutated)){
+                    summary.moved = true;
+                },
+                &Effect::Passed(..) | &Effect::Borrowed(..) => (),
+            }
+        }
+        summaries.insert(builder.name.clone(),FnSummary{ name: builder.name.clone(), params: params });
+    }
+
+    let mut changed = true;
+    while changed{
+        changed = false;
+        for builder in builders{
+            for &(index,_,ref effect) in &builder.effects{
+                let (callee, param, borrowed) = match effect{
+                    &Effect::Passed(ref callee, param) => (callee, param, false),
+                    &Effect::Borrowed(ref callee, param) => (callee, param, true),
+                    _ => continue,
+                };
+                let callee_summary = match summaries.get(callee){
+                    Some(summary) => summary.params.get(param).cloned(),
+                    None => None,
+                };
+                let summary = match summaries.get_mut(&builder.name).and_then(|s| s.params.get_mut(index)){
+                    Some(summary) => summary,
+                    None => continue,
+                };
+                changed |= match callee_summary{
+                    Some(ref callee_summary) if borrowed => summary.merge_borrow(callee_summary),
+                    Some(ref callee_summary) => summary.merge(callee_summary),
+                    // A function that is not summarized may change a
+                    // value it borrows
+                    None if borrowed => if !summary.mutated { summary.mutated = true; true } else { false },
+                    // A function that is not summarized may do anything
+                    // with the value
+                    None => if !summary.moved { summary.moved = true; true } else { false },
+                };
+            }
+        }
+    }
+    summaries
+}